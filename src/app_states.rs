@@ -1,5 +1,6 @@
 use bevy::app::Plugin;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 // Constants
 const NAME: &str = "app_states";
@@ -15,7 +16,20 @@ pub enum AppState {
 }
 
 // Sub States
-#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, SubStates)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Eq,
+    PartialEq,
+    Hash,
+    PartialOrd,
+    Ord,
+    SubStates,
+    Serialize,
+    Deserialize,
+)]
 #[source(AppState = AppState::Running)]
 pub enum LevelState {
     #[default]
@@ -26,6 +40,54 @@ pub enum LevelState {
     Level05,
 }
 
+impl LevelState {
+    pub const ALL: [LevelState; 5] = [
+        LevelState::Level01,
+        LevelState::Level02,
+        LevelState::Level03,
+        LevelState::Level04,
+        LevelState::Level05,
+    ];
+
+    /// following level, none for the last one
+    pub fn next(&self) -> Option<LevelState> {
+        LevelState::ALL
+            .iter()
+            .skip_while(|level| *level != self)
+            .nth(1)
+            .copied()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LevelState::Level01 => "First Steps",
+            LevelState::Level02 => "Gold Rush",
+            LevelState::Level03 => "Dead End",
+            LevelState::Level04 => "Stuck Door",
+            LevelState::Level05 => "Heart of Stone",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, SubStates)]
+#[source(AppState = AppState::MainMenu)]
+pub enum MenuState {
+    #[default]
+    Main,
+    LevelSelect,
+    Settings,
+}
+
+/// the game is paused in every state but unpaused
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, SubStates)]
+#[source(AppState = AppState::Running)]
+pub enum PauseState {
+    #[default]
+    Unpaused,
+    Paused,
+    Settings,
+}
+
 // Plugin
 pub struct AppStatesPlugin;
 
@@ -36,6 +98,8 @@ impl Plugin for AppStatesPlugin {
             .init_state::<AppState>()
             // sub states
             .add_sub_state::<LevelState>()
+            .add_sub_state::<MenuState>()
+            .add_sub_state::<PauseState>()
             // systems
             .add_systems(OnEnter(AppState::Quitting), quitting);
     }
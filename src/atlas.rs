@@ -0,0 +1,386 @@
+use bevy::{
+    app::Plugin,
+    asset::{AssetLoader, LoadContext, io::Reader},
+    platform::collections::{HashMap, HashSet},
+};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    controls::PlayerId,
+    lighting::LightSource,
+    sprites::SpriteSheetTile,
+    tiles::{Direction, TileLayer},
+};
+
+// Constants
+const NAME: &str = "atlas";
+
+/// later sheets override the tiles of earlier ones
+const ATLAS_FILES: [&str; 1] = ["Tilesheet/monochrome.atlas.ron"];
+
+// Plugin
+pub struct AtlasPlugin;
+
+impl Plugin for AtlasPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // assets
+            .init_asset::<SpriteAtlas>()
+            .init_asset_loader::<SpriteAtlasLoader>()
+            // systems
+            .add_systems(Startup, load_atlases)
+            .add_systems(
+                Update,
+                (build_tile_catalog).run_if(resource_exists::<LoadedAtlases>),
+            );
+    }
+}
+
+// Assets
+/// named tiles on one sprite sheet, loaded from `*.atlas.ron` files
+#[derive(Asset, TypePath, Debug)]
+pub struct SpriteAtlas {
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    pub columns: u32,
+    pub tiles: HashMap<SpriteSheetTile, TileDefinition>,
+    pub autotiles: HashMap<SpriteSheetTile, AutotileDefinition>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TileDefinition {
+    /// column and row on the sheet
+    pub at: (u32, u32),
+    /// linear rgb, white when missing
+    #[serde(default)]
+    pub tint: Option<(f32, f32, f32)>,
+    #[serde(default)]
+    pub traits: Vec<TileTrait>,
+    /// draw layer, objects when missing
+    #[serde(default)]
+    pub layer: TileLayer,
+}
+
+/// picks the sprite of a tile from its neighbours, see [`crate::autotile`]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AutotileDefinition {
+    #[serde(default)]
+    pub neighbourhood: Neighbourhood,
+    /// other tiles that count as neighbours, the tile itself always does
+    #[serde(default)]
+    pub connects: Vec<SpriteSheetTile>,
+    /// neighbour mask to column and row on the sheet
+    pub variants: HashMap<u8, (u32, u32)>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// edges only
+    #[default]
+    Four,
+    /// edges and corners
+    Eight,
+}
+
+/// gameplay components a tile gets when it is spawned
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TileTrait {
+    /// part of the level itself, not just a sprite on it
+    Tile,
+    Floor,
+    Solid,
+    Interactable,
+    Door,
+    Trigger,
+    Npc,
+    Exit,
+    Player(PlayerId),
+    Gold(i64),
+    Hearts(usize),
+    EmptyHeart,
+    /// special floors, see [`crate::tiles::FloorBehaviour`]
+    Ice,
+    Conveyor(Direction),
+    OneWay(Direction),
+    Mud,
+    /// linked with the other teleporters of the group, see [`crate::teleport`]
+    Teleporter(u8),
+    /// moving things on paths, see [`crate::paths`]
+    Hazard(usize),
+    Pusher,
+    Platform,
+    Pit,
+    /// shines on the tiles around it, see [`crate::lighting`]
+    Light(LightSource),
+}
+
+#[derive(Deserialize)]
+struct SpriteAtlasFile {
+    image: String,
+    tile_size: u32,
+    columns: u32,
+    rows: u32,
+    #[serde(default)]
+    gap: u32,
+    tiles: HashMap<SpriteSheetTile, TileDefinition>,
+    #[serde(default)]
+    autotiles: HashMap<SpriteSheetTile, AutotileDefinition>,
+}
+
+#[derive(Default)]
+pub struct SpriteAtlasLoader;
+
+#[derive(Debug, Error)]
+pub enum SpriteAtlasLoaderError {
+    #[error("could not read sprite atlas: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse sprite atlas: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for SpriteAtlasLoader {
+    type Asset = SpriteAtlas;
+    type Settings = ();
+    type Error = SpriteAtlasLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file = ron::de::from_bytes::<SpriteAtlasFile>(&bytes)?;
+        let layout = TextureAtlasLayout::from_grid(
+            UVec2::splat(file.tile_size),
+            file.columns,
+            file.rows,
+            Some(UVec2::splat(file.gap)),
+            None,
+        );
+        Ok(SpriteAtlas {
+            image: load_context.load(file.image),
+            layout: load_context.add_labeled_asset("layout".to_string(), layout),
+            columns: file.columns,
+            tiles: file.tiles,
+            autotiles: file.autotiles,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["atlas.ron"]
+    }
+}
+
+impl SpriteAtlas {
+    fn index(&self, (x, y): (u32, u32)) -> usize {
+        (y * self.columns + x) as usize
+    }
+
+    fn catalog_tile(&self, definition: &TileDefinition) -> CatalogTile {
+        CatalogTile {
+            image: self.image.clone(),
+            layout: self.layout.clone(),
+            index: self.index(definition.at),
+            color: definition
+                .tint
+                .map(|(r, g, b)| Color::linear_rgb(r, g, b))
+                .unwrap_or_default(),
+            traits: definition.traits.clone(),
+            layer: definition.layer,
+        }
+    }
+
+    fn catalog_autotile(
+        &self,
+        tile: &SpriteSheetTile,
+        definition: &AutotileDefinition,
+    ) -> Autotile {
+        let mut connects: HashSet<SpriteSheetTile> = definition.connects.iter().cloned().collect();
+        connects.insert(tile.clone());
+        Autotile {
+            neighbourhood: definition.neighbourhood,
+            connects,
+            variants: definition
+                .variants
+                .iter()
+                .map(|(mask, at)| (*mask, self.index(*at)))
+                .collect(),
+        }
+    }
+}
+
+// Components
+
+// Resources
+#[derive(Resource)]
+pub struct LoadedAtlases(pub Vec<Handle<SpriteAtlas>>);
+
+/// every tile of all loaded atlases, rebuilt when an atlas changes
+#[derive(Resource, Default, Debug)]
+pub struct TileCatalog {
+    tiles: HashMap<SpriteSheetTile, CatalogTile>,
+    autotiles: HashMap<SpriteSheetTile, Autotile>,
+}
+
+impl TileCatalog {
+    pub fn get(&self, tile: &SpriteSheetTile) -> Option<&CatalogTile> {
+        self.tiles.get(tile)
+    }
+
+    pub fn autotile(&self, tile: &SpriteSheetTile) -> Option<&Autotile> {
+        self.autotiles.get(tile)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CatalogTile {
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    pub index: usize,
+    /// default tint
+    pub color: Color,
+    pub traits: Vec<TileTrait>,
+    pub layer: TileLayer,
+}
+
+impl CatalogTile {
+    pub fn sprite(&self) -> Sprite {
+        Sprite {
+            image: self.image.clone(),
+            color: self.color,
+            texture_atlas: Some(TextureAtlas {
+                layout: self.layout.clone(),
+                index: self.index,
+            }),
+            ..default()
+        }
+    }
+}
+
+/// autotile rule with sheet indices, the variants have to be on the sheet of the tile
+#[derive(Debug, Clone)]
+pub struct Autotile {
+    pub neighbourhood: Neighbourhood,
+    pub connects: HashSet<SpriteSheetTile>,
+    pub variants: HashMap<u8, usize>,
+}
+
+// Events
+
+// Systems
+fn load_atlases(mut commands: Commands, asset_server: Res<AssetServer>) {
+    debug!("starting {}", NAME);
+    commands.insert_resource(LoadedAtlases(
+        ATLAS_FILES
+            .iter()
+            .map(|file| asset_server.load(*file))
+            .collect(),
+    ));
+}
+
+fn build_tile_catalog(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<SpriteAtlas>>,
+    loaded: Res<LoadedAtlases>,
+    atlases: Res<Assets<SpriteAtlas>>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    // levels can only spawn once every sheet is there
+    let Some(atlases) = loaded
+        .0
+        .iter()
+        .map(|handle| atlases.get(handle))
+        .collect::<Option<Vec<&SpriteAtlas>>>()
+    else {
+        return;
+    };
+    debug!("building tile catalog from {} atlases", atlases.len());
+    commands.insert_resource(catalog(&atlases));
+}
+
+// helper functions
+fn catalog(atlases: &[&SpriteAtlas]) -> TileCatalog {
+    let mut catalog = TileCatalog::default();
+    for atlas in atlases {
+        for (tile, definition) in atlas.tiles.iter() {
+            catalog
+                .tiles
+                .insert(tile.clone(), atlas.catalog_tile(definition));
+        }
+        for (tile, definition) in atlas.autotiles.iter() {
+            catalog
+                .autotiles
+                .insert(tile.clone(), atlas.catalog_autotile(tile, definition));
+        }
+    }
+    catalog
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    fn atlas(file: &str) -> SpriteAtlas {
+        let file = ron::de::from_str::<SpriteAtlasFile>(file).unwrap();
+        SpriteAtlas {
+            image: Handle::default(),
+            layout: Handle::default(),
+            columns: file.columns,
+            tiles: file.tiles,
+            autotiles: file.autotiles,
+        }
+    }
+
+    #[test]
+    fn should_let_later_atlases_override_tiles() {
+        // given
+        let base = atlas(
+            r#"(
+                image: "base.png",
+                tile_size: 16,
+                columns: 10,
+                rows: 4,
+                gap: 1,
+                tiles: {
+                    GoldCoin: (at: (2, 3), tint: Some((0.6, 0.6, 0.0)), traits: [Gold(1)]),
+                    Grass: (at: (5, 0), traits: [Tile, Floor]),
+                },
+            )"#,
+        );
+        let swapped = atlas(
+            r#"(
+                image: "swapped.png",
+                tile_size: 16,
+                columns: 8,
+                rows: 8,
+                tiles: {
+                    Grass: (at: (1, 1), traits: [Tile, Floor]),
+                    Custom("Barrel"): (at: (0, 2), traits: [Tile, Solid]),
+                },
+            )"#,
+        );
+
+        // when
+        let catalog = catalog(&[&base, &swapped]);
+
+        // then
+        let coin = catalog.get(&SpriteSheetTile::GoldCoin).unwrap();
+        assert_eq!(coin.index, 32);
+        assert_eq!(coin.traits, vec![TileTrait::Gold(1)]);
+        assert_eq!(catalog.get(&SpriteSheetTile::Grass).unwrap().index, 9);
+        let barrel = catalog
+            .get(&SpriteSheetTile::Custom("Barrel".to_string()))
+            .unwrap();
+        assert_eq!(barrel.traits, vec![TileTrait::Tile, TileTrait::Solid]);
+        assert_eq!(barrel.color, Color::default());
+    }
+}
@@ -0,0 +1,195 @@
+use bevy::{app::Plugin, platform::collections::HashSet};
+
+use AppState::Running;
+use bevy::prelude::*;
+
+use crate::{
+    app_states::AppState,
+    atlas::{Autotile, Neighbourhood, TileCatalog},
+    grid::GridIndex,
+    sprites::SpriteSheetTile,
+    tiles::TileCoordinate,
+};
+
+// Constants
+const NAME: &str = "autotile";
+
+// neighbour mask bits, corners only count when both edges next to them connect
+const NORTH: u8 = 1;
+const EAST: u8 = 2;
+const SOUTH: u8 = 4;
+const WEST: u8 = 8;
+const NORTH_EAST: u8 = 16;
+const SOUTH_EAST: u8 = 32;
+const SOUTH_WEST: u8 = 64;
+const NORTH_WEST: u8 = 128;
+const EDGES: u8 = NORTH | EAST | SOUTH | WEST;
+
+// Plugin
+pub struct AutotilePlugin;
+
+impl Plugin for AutotilePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // resources
+            .init_resource::<DirtyCells>()
+            // observers
+            .add_observer(tile_added)
+            .add_observer(tile_removed)
+            // systems
+            .add_systems(Update, (update_autotiles).run_if(in_state(Running)));
+    }
+}
+
+// Components
+
+// Resources
+/// cells whose autotiles have to pick their sprite again
+#[derive(Resource, Default, Debug)]
+struct DirtyCells(HashSet<IVec2>);
+
+impl DirtyCells {
+    fn mark_around(&mut self, coordinate: &TileCoordinate) {
+        for x in -1..=1 {
+            for y in -1..=1 {
+                self.0.insert(coordinate.cell() + IVec2::new(x, y));
+            }
+        }
+    }
+}
+
+impl Autotile {
+    /// exact variant, the edges only variant or none to keep the base sprite
+    fn variant(&self, mask: u8) -> Option<usize> {
+        self.variants
+            .get(&mask)
+            .or_else(|| self.variants.get(&(mask & EDGES)))
+            .copied()
+    }
+}
+
+// Events
+
+// Systems
+fn tile_added(
+    trigger: Trigger<OnInsert, SpriteSheetTile>,
+    coordinates: Query<&TileCoordinate>,
+    mut dirty: ResMut<DirtyCells>,
+) {
+    if let Ok(coordinate) = coordinates.get(trigger.target()) {
+        dirty.mark_around(coordinate);
+    }
+}
+
+fn tile_removed(
+    trigger: Trigger<OnRemove, SpriteSheetTile>,
+    coordinates: Query<&TileCoordinate>,
+    mut dirty: ResMut<DirtyCells>,
+) {
+    // the neighbours of a despawned door or wall lose a connection
+    if let Ok(coordinate) = coordinates.get(trigger.target()) {
+        dirty.mark_around(coordinate);
+    }
+}
+
+fn update_autotiles(
+    mut dirty: ResMut<DirtyCells>,
+    catalog: Option<Res<TileCatalog>>,
+    grid: Res<GridIndex>,
+    tiles: Query<&SpriteSheetTile>,
+    mut sprites: Query<(&SpriteSheetTile, &mut Sprite)>,
+) {
+    let Some(catalog) = catalog else {
+        return;
+    };
+    if dirty.0.is_empty() {
+        return;
+    }
+    debug!("updating {} for {} cells", NAME, dirty.0.len());
+    for cell in dirty.0.drain() {
+        for entity in grid.at(cell) {
+            let Ok((tile, mut sprite)) = sprites.get_mut(entity) else {
+                continue;
+            };
+            let Some(rule) = catalog.autotile(tile) else {
+                continue;
+            };
+            let mask = neighbour_mask(rule.neighbourhood, |x, y| {
+                grid.at(cell + IVec2::new(x, y))
+                    .any(|e| tiles.get(e).is_ok_and(|t| rule.connects.contains(t)))
+            });
+            let Some(index) = rule.variant(mask).or(catalog.get(tile).map(|t| t.index)) else {
+                continue;
+            };
+            if let Some(atlas) = sprite.texture_atlas.as_mut()
+                && atlas.index != index
+            {
+                atlas.index = index;
+            }
+        }
+    }
+}
+
+// helper functions
+/// bits of the connected neighbours, corners only with both edges next to them
+fn neighbour_mask(neighbourhood: Neighbourhood, connected: impl Fn(i32, i32) -> bool) -> u8 {
+    let mut mask = 0;
+    for (bit, x, y) in [(NORTH, 0, 1), (EAST, 1, 0), (SOUTH, 0, -1), (WEST, -1, 0)] {
+        if connected(x, y) {
+            mask |= bit;
+        }
+    }
+    if neighbourhood == Neighbourhood::Eight {
+        for (bit, edges, x, y) in [
+            (NORTH_EAST, NORTH | EAST, 1, 1),
+            (SOUTH_EAST, SOUTH | EAST, 1, -1),
+            (SOUTH_WEST, SOUTH | WEST, -1, -1),
+            (NORTH_WEST, NORTH | WEST, -1, 1),
+        ] {
+            if mask & edges == edges && connected(x, y) {
+                mask |= bit;
+            }
+        }
+    }
+    mask
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn should_only_count_corners_next_to_connected_edges() {
+        // given
+        let cells = [(0, 1), (1, 1), (1, 0), (-1, -1)];
+        let connected = |x, y| cells.contains(&(x, y));
+
+        // when
+        let four = neighbour_mask(Neighbourhood::Four, connected);
+        let eight = neighbour_mask(Neighbourhood::Eight, connected);
+
+        // then
+        assert_eq!(four, NORTH | EAST);
+        assert_eq!(eight, NORTH | EAST | NORTH_EAST);
+    }
+
+    #[test]
+    fn should_fall_back_to_edges_only_variant() {
+        // given
+        let rule = Autotile {
+            neighbourhood: Neighbourhood::Eight,
+            connects: HashSet::default(),
+            variants: [(NORTH | EAST, 7), (0, 1)].into_iter().collect(),
+        };
+
+        // when
+        let with_corner = rule.variant(NORTH | EAST | NORTH_EAST);
+        let missing = rule.variant(SOUTH);
+
+        // then
+        assert_eq!(with_corner, Some(7));
+        assert_eq!(missing, None);
+    }
+}
@@ -1,9 +1,17 @@
-use bevy::app::Plugin;
+use bevy::{app::Plugin, platform::collections::HashSet};
 
 use AppState::Running;
 use bevy::prelude::*;
+use serde::Deserialize;
 
-use crate::app_states::AppState;
+use crate::{
+    app_states::AppState,
+    grid::{GridIndex, GridRole},
+    movement::MoveDirections,
+    settings::Settings,
+    sprites::{SpawnSprite, SpriteSheetTile},
+    tiles::{Direction, TileCoordinate},
+};
 
 // Constants
 const NAME: &str = "controls";
@@ -15,63 +23,105 @@ impl Plugin for ControlsPlugin {
     fn build(&self, app: &mut App) {
         app
             // Events
-            .add_event::<Right>()
-            .add_event::<Left>()
-            .add_event::<Up>()
-            .add_event::<Down>()
+            .add_event::<MoveIntent>()
+            // resources
+            .init_resource::<ControlLocks>()
             // Systems
             .add_systems(OnEnter(Running), start_controls)
-            .add_systems(Update, (update_controls).run_if(in_state(Running)))
+            .add_systems(
+                Update,
+                (update_controls, spawn_second_player).run_if(in_state(Running)),
+            )
             .add_systems(OnExit(Running), stop_controls);
     }
 }
 
 // Components
-#[derive(Component)]
-pub struct PlayerControlled;
-
-// Resources
+/// moved by the control events of its player
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerControlled(pub PlayerId);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+pub enum PlayerId {
+    #[default]
+    One,
+    Two,
+}
 
-// Events
-#[derive(Event)]
-pub struct Right;
+impl PlayerId {
+    pub const ALL: [PlayerId; 2] = [PlayerId::One, PlayerId::Two];
 
-#[derive(Event)]
-pub struct Left;
+    pub fn number(&self) -> usize {
+        match self {
+            PlayerId::One => 1,
+            PlayerId::Two => 2,
+        }
+    }
+}
 
-#[derive(Event)]
-pub struct Up;
+// Resources
+/// player input is ignored while any lock is held
+#[derive(Resource, Default, Debug)]
+pub struct ControlLocks(pub HashSet<ControlLock>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ControlLock {
+    /// dialogue box is open and takes over the input
+    Dialogue,
+    /// pause menu is open
+    Pause,
+    /// level transition is playing
+    Transition,
+}
 
-#[derive(Event)]
-pub struct Down;
+// Events
+/// a player wants to move one tile. keyboard, gamepads, tutorials and anything else steering
+/// players speak this.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveIntent {
+    pub player: PlayerId,
+    pub direction: Direction,
+}
 
 // Systems
 fn start_controls(mut _commands: Commands) {
     debug!("starting {}", NAME);
 }
 
-fn update_controls(
-    mut right: EventReader<Right>,
-    mut left: EventReader<Left>,
-    mut up: EventReader<Up>,
-    mut down: EventReader<Down>,
-) {
+fn update_controls(mut intents: EventReader<MoveIntent>) {
     debug!("updating {}", NAME);
 
-    for _ in left.read() {
-        debug!("received left event");
-    }
-
-    for _ in right.read() {
-        debug!("received right event");
+    for e in intents.read() {
+        debug!("received {:?} intent for {:?}", e.direction, e.player);
     }
+}
 
-    for _ in up.read() {
-        debug!("received up event");
+/// levels only spawn the first player, in co-op the second one joins on a free tile next to it
+fn spawn_second_player(
+    added_players: Query<(&PlayerControlled, &TileCoordinate), Added<PlayerControlled>>,
+    grid: Res<GridIndex>,
+    settings: Res<Settings>,
+    mut spawn_sprite: EventWriter<SpawnSprite>,
+) {
+    if settings.players < 2 {
+        return;
     }
-
-    for _ in down.read() {
-        debug!("received down event");
+    for (_, coordinate) in added_players.iter().filter(|(p, _)| p.0 == PlayerId::One) {
+        let free = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .into_iter()
+            .map(|(x, y)| TileCoordinate {
+                x: coordinate.x + x,
+                y: coordinate.y + y,
+                z: coordinate.z,
+            })
+            .find(|c| grid.first(c.cell(), GridRole::Solid).is_none())
+            .unwrap_or(coordinate.clone());
+        debug!("spawning second player on {}", free);
+        spawn_sprite.write(SpawnSprite {
+            coordinate: free,
+            tile: SpriteSheetTile::Player02,
+            ..default()
+        });
     }
 }
 
@@ -80,29 +130,61 @@ fn stop_controls(mut _commands: Commands) {
 }
 
 // helper functions
+/// run condition for input handling
+pub fn controls_unlocked(locks: Res<ControlLocks>) -> bool {
+    locks.0.is_empty()
+}
+
+/// directions of one player's newly pressed buttons. with diagonals allowed two perpendicular
+/// buttons pressed in the same frame go diagonally, any other press moves right away.
+pub fn move_intents(just_pressed: &[Direction], directions: MoveDirections) -> Vec<Direction> {
+    if directions == MoveDirections::Four {
+        return just_pressed.to_vec();
+    }
+    let mut intents = Vec::new();
+    let mut used: Vec<Direction> = Vec::new();
+    for pressed in just_pressed {
+        if used.contains(pressed) {
+            continue;
+        }
+        used.push(*pressed);
+        let diagonal = just_pressed
+            .iter()
+            .filter(|p| !used.contains(p))
+            .find_map(|p| pressed.combine(*p).map(|d| (*p, d)));
+        match diagonal {
+            Some((partner, direction)) => {
+                used.push(partner);
+                intents.push(direction);
+            }
+            None => intents.push(*pressed),
+        }
+    }
+    intents
+}
 
 // tests
 #[cfg(test)]
 mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
-    //use super::*;
+    use super::*;
 
-    /*
     #[test]
-    fn should_test_something() {
+    fn should_combine_perpendicular_buttons_only_with_diagonals() {
         // given
-        //let mut _app = App::new();
+        let together = [Direction::Up, Direction::Right];
 
         // when
-        //app.add_event::<HealthDamageReceived>();
-        //app.add_systems(Update, damage_received_listener);
-        //let entity = app.borrow_mut().world.spawn(Health(100)).id();
-        //app.borrow_mut().world.resource_mut::<Events<HealthDamageReceived>>().send(HealthDamageReceived { entity, damage: 10 });
-        //app.update();
+        let four = move_intents(&together, MoveDirections::Four);
+        let eight = move_intents(&together, MoveDirections::Eight);
+        // the partner of a held button comes a frame later
+        let alone = move_intents(&[Direction::Up], MoveDirections::Eight);
+        let opposite = move_intents(&[Direction::Up, Direction::Down], MoveDirections::Eight);
 
         // then
-        //assert!(app.world.get::<Health>(entity).is_some());
-        //assert_eq!(app.world.get::<Health>(entity).unwrap().0, 90);
+        assert_eq!(four, vec![Direction::Up, Direction::Right]);
+        assert_eq!(eight, vec![Direction::UpRight]);
+        assert_eq!(alone, vec![Direction::Up]);
+        assert_eq!(opposite, vec![Direction::Up, Direction::Down]);
     }
-    */
 }
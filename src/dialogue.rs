@@ -0,0 +1,546 @@
+use bevy::{
+    app::Plugin,
+    asset::{AssetLoader, LoadContext, io::Reader},
+    platform::collections::{HashMap, HashSet},
+};
+
+use AppState::Running;
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    app_states::AppState,
+    controls::{ControlLock, ControlLocks},
+    gold::{Gold, GoldChanged},
+    health::{Health, Hearts},
+    interaction::{Interacted, InteractionId, InteractionTarget},
+    movement::InteractionTriggered,
+    sprites::{SpawnSprite, SpriteSheetTile},
+    tiles::{DoorOpened, DoorTile, TileCoordinate},
+};
+
+// Constants
+const NAME: &str = "dialogue";
+
+const CHOICE_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+// Plugin
+pub struct DialoguePlugin;
+
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // assets
+            .init_asset::<DialogueTree>()
+            .init_asset_loader::<DialogueTreeLoader>()
+            // events
+            .add_event::<DialogueStarted>()
+            .add_event::<DialogueEnded>()
+            // systems
+            .add_systems(OnEnter(Running), start_dialogue)
+            .add_systems(
+                Update,
+                (start_conversation, dialogue_input, show_dialogue_box)
+                    .chain()
+                    .run_if(in_state(Running)),
+            )
+            .add_systems(OnExit(Running), stop_dialogue);
+    }
+}
+
+// Assets
+/// data driven dialogue tree, loaded from `*.dialogue.ron` files
+#[derive(Asset, TypePath, Deserialize, Debug, Default)]
+pub struct DialogueTree {
+    pub start: String,
+    pub nodes: HashMap<String, DialogueNode>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct DialogueNode {
+    pub speaker: String,
+    pub text: String,
+    /// choices offered to the player. without choices any key continues to `next`
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+    /// follow up node without choices. dialogue ends if there is none
+    #[serde(default)]
+    pub next: Option<String>,
+    /// side effects when the node is shown
+    #[serde(default)]
+    pub effects: Vec<DialogueEffect>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DialogueChoice {
+    pub text: String,
+    #[serde(default)]
+    pub next: Option<String>,
+    /// choice is only offered if all conditions hold
+    #[serde(default)]
+    pub conditions: Vec<DialogueCondition>,
+    #[serde(default)]
+    pub effects: Vec<DialogueEffect>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum DialogueCondition {
+    GoldAtLeast(i64),
+    HeartsAtLeast(usize),
+    Flag(String),
+    NotFlag(String),
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum DialogueEffect {
+    /// negative amounts take gold away
+    GiveGold(i64),
+    GiveHearts(usize),
+    SetFlag(String),
+    ClearFlag(String),
+    /// replaces the door on the given cell with an open door
+    OpenDoor(i32, i32),
+    /// same as pulling a lever with this [InteractionId]
+    Trigger(u32),
+}
+
+#[derive(Default)]
+pub struct DialogueTreeLoader;
+
+#[derive(Debug, Error)]
+pub enum DialogueTreeLoaderError {
+    #[error("could not read dialogue: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse dialogue: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for DialogueTreeLoader {
+    type Asset = DialogueTree;
+    type Settings = ();
+    type Error = DialogueTreeLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes::<DialogueTree>(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["dialogue.ron"]
+    }
+}
+
+// Components
+/// npc marker, npcs are solid and interactable, bumping into them starts their [Dialogue]
+#[derive(Component)]
+pub struct NpcTile;
+
+/// dialogue of an npc
+#[derive(Component)]
+pub struct Dialogue(pub Handle<DialogueTree>);
+
+#[derive(Component)]
+struct DialogueBox;
+
+// Resources
+/// story flags set by dialogues, kept for the whole run
+#[derive(Resource, Default, Debug)]
+pub struct StoryFlags(pub HashSet<String>);
+
+/// conversation in progress, input is locked while this exists
+#[derive(Resource)]
+struct ActiveDialogue {
+    tree: Handle<DialogueTree>,
+    node: String,
+    player: Entity,
+    /// index in the node's choices for every visible choice
+    visible_choices: Vec<usize>,
+}
+
+// Events
+#[derive(Event)]
+#[allow(dead_code)]
+pub struct DialogueStarted {
+    pub player: Entity,
+    pub npc: Entity,
+}
+
+#[derive(Event)]
+#[allow(dead_code)]
+pub struct DialogueEnded {
+    pub player: Entity,
+}
+
+// Systems
+fn start_dialogue(mut commands: Commands) {
+    debug!("starting {}", NAME);
+    commands.init_resource::<StoryFlags>();
+}
+
+fn start_conversation(
+    mut commands: Commands,
+    mut triggered: EventReader<InteractionTriggered>,
+    npcs: Query<&Dialogue>,
+    active: Option<Res<ActiveDialogue>>,
+    trees: Res<Assets<DialogueTree>>,
+    mut locks: ResMut<ControlLocks>,
+    mut started: EventWriter<DialogueStarted>,
+) {
+    for t in triggered.read() {
+        if active.is_some() {
+            continue;
+        }
+        let Ok(dialogue) = npcs.get(t.interacted_with) else {
+            continue;
+        };
+        let Some(tree) = trees.get(&dialogue.0) else {
+            debug!("dialogue for {} not loaded yet", t.interacted_with);
+            continue;
+        };
+        debug!("starting conversation with {}", t.interacted_with);
+        commands.insert_resource(ActiveDialogue {
+            tree: dialogue.0.clone(),
+            node: tree.start.clone(),
+            player: t.triggered_by,
+            visible_choices: Vec::new(),
+        });
+        locks.0.insert(ControlLock::Dialogue);
+        started.write(DialogueStarted {
+            player: t.triggered_by,
+            npc: t.interacted_with,
+        });
+    }
+}
+
+fn dialogue_input(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    active: Option<ResMut<ActiveDialogue>>,
+    trees: Res<Assets<DialogueTree>>,
+    mut locks: ResMut<ControlLocks>,
+    mut flags: ResMut<StoryFlags>,
+    mut effects: EffectTargets,
+    mut ended: EventWriter<DialogueEnded>,
+) {
+    let Some(mut active) = active else {
+        return;
+    };
+    let Some(node) = trees
+        .get(&active.tree)
+        .and_then(|tree| tree.nodes.get(&active.node))
+    else {
+        warn!("dialogue node {} not found", active.node);
+        end_conversation(&mut commands, &mut locks, &mut ended, active.player);
+        return;
+    };
+
+    let next = if keyboard_input.just_pressed(KeyCode::Escape) {
+        Some(None)
+    } else if active.visible_choices.is_empty() {
+        keyboard_input
+            .any_just_pressed([KeyCode::Space, KeyCode::Enter])
+            .then(|| node.next.clone())
+    } else {
+        CHOICE_KEYS
+            .iter()
+            .zip(active.visible_choices.iter())
+            .find(|(key, _)| keyboard_input.just_pressed(**key))
+            .map(|(_, index)| {
+                let choice = &node.choices[*index];
+                for effect in choice.effects.iter() {
+                    effects.apply(effect, active.player, &mut flags);
+                }
+                choice.next.clone()
+            })
+    };
+
+    match next {
+        Some(Some(next)) => {
+            debug!("dialogue continues with {}", next);
+            active.node = next;
+        }
+        Some(None) => {
+            end_conversation(&mut commands, &mut locks, &mut ended, active.player);
+        }
+        None => (),
+    }
+}
+
+fn show_dialogue_box(
+    mut commands: Commands,
+    active: Option<ResMut<ActiveDialogue>>,
+    trees: Res<Assets<DialogueTree>>,
+    boxes: Query<Entity, With<DialogueBox>>,
+    mut flags: ResMut<StoryFlags>,
+    mut effects: EffectTargets,
+) {
+    let Some(mut active) = active else {
+        for b in boxes.iter() {
+            commands.entity(b).despawn();
+        }
+        return;
+    };
+    if !active.is_changed() {
+        return;
+    }
+    let Some(node) = trees
+        .get(&active.tree)
+        .and_then(|tree| tree.nodes.get(&active.node))
+    else {
+        return;
+    };
+
+    for effect in node.effects.iter() {
+        effects.apply(effect, active.player, &mut flags);
+    }
+
+    let (gold, health) = effects.players.get(active.player).unwrap_or((None, None));
+    let visible_choices: Vec<usize> = node
+        .choices
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| {
+            c.conditions
+                .iter()
+                .all(|condition| condition_holds(condition, gold, health, &flags))
+        })
+        .map(|(i, _)| i)
+        .take(CHOICE_KEYS.len())
+        .collect();
+
+    for b in boxes.iter() {
+        commands.entity(b).despawn();
+    }
+    let lines: Vec<String> = if visible_choices.is_empty() {
+        vec!["[space] ...".to_string()]
+    } else {
+        visible_choices
+            .iter()
+            .enumerate()
+            .map(|(key, i)| format!("[{}] {}", key + 1, node.choices[*i].text))
+            .collect()
+    };
+    commands.spawn(dialogue_box()).with_children(|parent| {
+        parent.spawn((
+            Text::new(node.speaker.clone()),
+            TextFont {
+                font_size: 28.0,
+                ..default()
+            },
+            TextColor(Color::srgb(0.9, 0.8, 0.3)),
+        ));
+        parent.spawn(Text::new(node.text.clone()));
+        for line in lines {
+            parent.spawn((Text::new(line), TextColor(Color::srgb(0.7, 0.7, 0.7))));
+        }
+    });
+
+    // bypass change detection, otherwise the box would be rebuilt every frame
+    active.bypass_change_detection().visible_choices = visible_choices;
+}
+
+fn stop_dialogue(mut commands: Commands, boxes: Query<Entity, With<DialogueBox>>) {
+    debug!("stopping {}", NAME);
+    for b in boxes.iter() {
+        commands.entity(b).despawn();
+    }
+    commands.remove_resource::<ActiveDialogue>();
+    commands.remove_resource::<StoryFlags>();
+}
+
+// helper functions
+/// everything a dialogue effect is allowed to touch
+#[derive(bevy::ecs::system::SystemParam)]
+struct EffectTargets<'w, 's> {
+    players: Query<'w, 's, (Option<&'static mut Gold>, Option<&'static mut Health>)>,
+    doors: Query<'w, 's, (Entity, &'static TileCoordinate, &'static Sprite), With<DoorTile>>,
+    targets: Query<'w, 's, (Entity, &'static InteractionTarget)>,
+    gold_changed: EventWriter<'w, GoldChanged>,
+    interacted: EventWriter<'w, Interacted>,
+    spawn_sprite: EventWriter<'w, SpawnSprite>,
+    door_opened: EventWriter<'w, DoorOpened>,
+}
+
+impl EffectTargets<'_, '_> {
+    fn apply(&mut self, effect: &DialogueEffect, player: Entity, flags: &mut StoryFlags) {
+        debug!("applying dialogue effect {:?}", effect);
+        match effect {
+            DialogueEffect::GiveGold(coins) => {
+                if let Ok((Some(mut gold), _)) = self.players.get_mut(player) {
+                    gold.coins += coins;
+                    self.gold_changed.write(GoldChanged {
+                        player,
+                        coins: Gold { coins: *coins },
+                    });
+                }
+            }
+            DialogueEffect::GiveHearts(hearts) => {
+                if let Ok((_, Some(mut health))) = self.players.get_mut(player) {
+                    health.hearts = Hearts((health.hearts.0 + hearts).min(health.max.0));
+                }
+            }
+            DialogueEffect::SetFlag(flag) => {
+                flags.0.insert(flag.clone());
+            }
+            DialogueEffect::ClearFlag(flag) => {
+                flags.0.remove(flag);
+            }
+            DialogueEffect::OpenDoor(x, y) => {
+                for (door, coordinate, sprite) in self.doors.iter() {
+                    if coordinate.x == *x && coordinate.y == *y {
+                        self.door_opened.write(DoorOpened {
+                            coordinate: coordinate.clone(),
+                            sprite: sprite.clone(),
+                        });
+                        self.spawn_sprite.write(SpawnSprite {
+                            coordinate: coordinate.clone(),
+                            tile: SpriteSheetTile::OpenDoor,
+                            replaces: Some(door),
+                            ..default()
+                        });
+                    }
+                }
+            }
+            DialogueEffect::Trigger(id) => {
+                let id = InteractionId(*id);
+                for (target, _) in self.targets.iter().filter(|(_, t)| t.0 == id) {
+                    self.interacted.write(Interacted(target));
+                }
+            }
+        }
+    }
+}
+
+fn condition_holds(
+    condition: &DialogueCondition,
+    gold: Option<&Gold>,
+    health: Option<&Health>,
+    flags: &StoryFlags,
+) -> bool {
+    match condition {
+        DialogueCondition::GoldAtLeast(coins) => gold.is_some_and(|g| g.coins >= *coins),
+        DialogueCondition::HeartsAtLeast(hearts) => health.is_some_and(|h| h.hearts.0 >= *hearts),
+        DialogueCondition::Flag(flag) => flags.0.contains(flag),
+        DialogueCondition::NotFlag(flag) => !flags.0.contains(flag),
+    }
+}
+
+fn end_conversation(
+    commands: &mut Commands,
+    locks: &mut ControlLocks,
+    ended: &mut EventWriter<DialogueEnded>,
+    player: Entity,
+) {
+    debug!("conversation ended");
+    commands.remove_resource::<ActiveDialogue>();
+    locks.0.remove(&ControlLock::Dialogue);
+    ended.write(DialogueEnded { player });
+}
+
+fn dialogue_box() -> impl Bundle + use<> {
+    (
+        DialogueBox,
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(10.0),
+            right: Val::Percent(10.0),
+            bottom: Val::Px(24.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(6.0),
+            padding: UiRect::all(Val::Px(12.0)),
+            border: UiRect::all(Val::Px(2.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.05, 0.05, 0.05, 0.9)),
+        BorderColor(Color::srgb(0.6, 0.6, 0.6)),
+    )
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn should_check_conditions_against_gold_hearts_and_flags() {
+        // given
+        let gold = Gold { coins: 5 };
+        let health = Health {
+            hearts: Hearts(1),
+            max: Hearts(3),
+        };
+        let mut flags = StoryFlags::default();
+        flags.0.insert("met_mechanic".to_string());
+
+        // when
+        let enough_gold = DialogueCondition::GoldAtLeast(5);
+        let too_many_hearts = DialogueCondition::HeartsAtLeast(2);
+        let flag = DialogueCondition::Flag("met_mechanic".to_string());
+        let not_flag = DialogueCondition::NotFlag("met_mechanic".to_string());
+
+        // then
+        assert!(condition_holds(
+            &enough_gold,
+            Some(&gold),
+            Some(&health),
+            &flags
+        ));
+        assert!(!condition_holds(&enough_gold, None, Some(&health), &flags));
+        assert!(!condition_holds(
+            &too_many_hearts,
+            Some(&gold),
+            Some(&health),
+            &flags
+        ));
+        assert!(condition_holds(&flag, None, None, &flags));
+        assert!(!condition_holds(&not_flag, None, None, &flags));
+    }
+
+    #[test]
+    fn should_parse_dialogue_tree() {
+        // given
+        let source = r#"(
+            start: "hello",
+            nodes: {
+                "hello": (
+                    speaker: "Mechanic",
+                    text: "Need a hand?",
+                    choices: [
+                        (text: "Open the door", conditions: [GoldAtLeast(5)], effects: [GiveGold(-5), Trigger(123)]),
+                        (text: "Bye"),
+                    ],
+                ),
+            },
+        )"#;
+
+        // when
+        let tree = ron::de::from_str::<DialogueTree>(source).unwrap();
+
+        // then
+        let node = tree.nodes.get(&tree.start).unwrap();
+        assert_eq!(node.choices.len(), 2);
+        assert_eq!(
+            node.choices[0].effects,
+            vec![DialogueEffect::GiveGold(-5), DialogueEffect::Trigger(123)]
+        );
+        assert!(node.choices[1].next.is_none());
+    }
+}
@@ -0,0 +1,358 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::app::Plugin;
+
+use AppState::Running;
+use bevy::prelude::*;
+
+use crate::{
+    app_states::AppState,
+    atlas::TileCatalog,
+    game_camera::GameCamera,
+    gold::PlayerPickedUpGoldCoins,
+    health::PickedUpHearts,
+    movement::MoveBlocked,
+    settings::Settings,
+    sprites::{MySprite, SPRITE_DIM, SPRITE_SCALE, SpriteSheetTile},
+    tiles::{DoorOpened, TileCoordinate},
+    tween::{DespawnAfterTween, Tween, TweenTarget, TweenTrack},
+};
+
+// Constants
+const NAME: &str = "effects";
+
+/// particles and bursts are drawn above the level
+const EFFECTS_Z: f32 = 10.0;
+
+// Plugin
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // resources
+            .init_resource::<EffectsConfig>()
+            // systems
+            .add_systems(PreUpdate, unshake_camera)
+            .add_systems(
+                Update,
+                (
+                    bump_on_blocked,
+                    sparkle_on_gold,
+                    flash_on_hearts,
+                    burst_on_doors,
+                )
+                    .run_if(in_state(Running)),
+            )
+            .add_systems(
+                PostUpdate,
+                shake_camera.before(TransformSystem::TransformPropagate),
+            )
+            .add_systems(OnExit(Running), stop_effects);
+    }
+}
+
+// Components
+/// shakes the camera around its position until the timer runs out
+#[derive(Component, Debug)]
+pub struct CameraShake {
+    timer: Timer,
+    strength: f32,
+    /// applied in post update and taken back in pre update, so camera movement stays unaware
+    offset: Vec3,
+}
+
+// Resources
+/// tuning of the gameplay effects, motion effects are skipped with
+/// [`Settings::reduced_motion`]
+#[derive(Resource, Debug, Clone)]
+pub struct EffectsConfig {
+    /// share of a tile a blocked mover bumps toward the obstacle
+    pub recoil: f32,
+    /// max camera offset in pixels
+    pub shake_strength: f32,
+    pub shake_duration: Duration,
+    pub sparkles: usize,
+    /// in tiles
+    pub sparkle_distance: f32,
+    pub sparkle_duration: Duration,
+    pub heart_flash: Color,
+    pub flash_duration: Duration,
+    pub door_burst_duration: Duration,
+}
+
+impl Default for EffectsConfig {
+    fn default() -> Self {
+        EffectsConfig {
+            recoil: 0.2,
+            shake_strength: 4.0,
+            shake_duration: Duration::from_millis(150),
+            sparkles: 6,
+            sparkle_distance: 0.75,
+            sparkle_duration: Duration::from_millis(400),
+            heart_flash: Color::linear_rgb(1.0, 0.2, 0.2),
+            flash_duration: Duration::from_millis(300),
+            door_burst_duration: Duration::from_millis(300),
+        }
+    }
+}
+
+// Events
+
+// Systems
+fn bump_on_blocked(
+    mut commands: Commands,
+    mut blocked: EventReader<MoveBlocked>,
+    movers: Query<(&Transform, &TileCoordinate, Has<Tween>)>,
+    obstacles: Query<&TileCoordinate>,
+    cameras: Query<Entity, With<GameCamera>>,
+    config: Res<EffectsConfig>,
+    settings: Res<Settings>,
+) {
+    debug!("updating {}", NAME);
+    if settings.reduced_motion {
+        blocked.clear();
+        return;
+    }
+    let mut shake = false;
+    for b in blocked.read() {
+        shake = true;
+        let (Ok((transform, from, moving)), Ok(to)) =
+            (movers.get(b.mover), obstacles.get(b.blocked_by))
+        else {
+            continue;
+        };
+        // translations are absolute, a running move must not be overwritten
+        if moving {
+            continue;
+        }
+        let start = transform.translation;
+        let towards: Vec3 = TileCoordinate {
+            x: to.x - from.x,
+            y: to.y - from.y,
+            z: 0,
+        }
+        .into();
+        let end = start + towards * config.recoil;
+        let half = config.shake_duration / 2;
+        commands
+            .entity(b.mover)
+            .insert(Tween::new(TweenTrack::sequence(vec![
+                TweenTrack::step(
+                    TweenTarget::Translation(start, end),
+                    half,
+                    EaseFunction::QuadraticOut,
+                ),
+                TweenTrack::step(
+                    TweenTarget::Translation(end, start),
+                    half,
+                    EaseFunction::QuadraticIn,
+                ),
+            ])));
+    }
+    if shake {
+        for camera in cameras.iter() {
+            commands.entity(camera).insert(CameraShake {
+                timer: Timer::new(config.shake_duration, TimerMode::Once),
+                strength: config.shake_strength,
+                offset: Vec3::ZERO,
+            });
+        }
+    }
+}
+
+fn sparkle_on_gold(
+    mut commands: Commands,
+    mut gold: EventReader<PlayerPickedUpGoldCoins>,
+    players: Query<&TileCoordinate>,
+    catalog: Option<Res<TileCatalog>>,
+    config: Res<EffectsConfig>,
+    settings: Res<Settings>,
+) {
+    let sparkle = catalog.and_then(|c| c.get(&SpriteSheetTile::GoldCoin).map(|t| t.sprite()));
+    let Some(sparkle) = sparkle.filter(|_| !settings.reduced_motion) else {
+        gold.clear();
+        return;
+    };
+    for g in gold.read() {
+        let Ok(coordinate) = players.get(g.player) else {
+            continue;
+        };
+        let mut center: Vec3 = coordinate.clone().into();
+        center.z = EFFECTS_Z;
+        let distance = config.sparkle_distance * SPRITE_DIM as f32 * SPRITE_SCALE;
+        for i in 0..config.sparkles {
+            let angle = TAU * i as f32 / config.sparkles as f32;
+            let end = center + Vec3::new(angle.cos(), angle.sin(), 0.0) * distance;
+            commands.spawn((
+                MySprite,
+                Name::new("Sparkle"),
+                sparkle.clone(),
+                Transform::from_translation(center).with_scale(Vec3::splat(SPRITE_SCALE * 0.4)),
+                Tween::new(TweenTrack::parallel(vec![
+                    TweenTrack::step(
+                        TweenTarget::Translation(center, end),
+                        config.sparkle_duration,
+                        EaseFunction::QuadraticOut,
+                    ),
+                    TweenTrack::step(
+                        TweenTarget::SpriteAlpha(1.0, 0.0),
+                        config.sparkle_duration,
+                        EaseFunction::QuadraticIn,
+                    ),
+                ])),
+                DespawnAfterTween,
+            ));
+        }
+    }
+}
+
+fn flash_on_hearts(
+    mut commands: Commands,
+    mut hearts: EventReader<PickedUpHearts>,
+    mut players: Query<(&Sprite, Option<&mut Tween>)>,
+    config: Res<EffectsConfig>,
+) {
+    for h in hearts.read() {
+        let Ok((sprite, tween)) = players.get_mut(h.entity) else {
+            continue;
+        };
+        let flash = TweenTrack::step(
+            TweenTarget::SpriteColor(config.heart_flash, sprite.color),
+            config.flash_duration,
+            EaseFunction::QuadraticIn,
+        );
+        // the flash runs alongside a move instead of cutting it short
+        match tween {
+            Some(mut tween) => tween.join(flash),
+            None => {
+                commands.entity(h.entity).insert(Tween::new(flash));
+            }
+        }
+    }
+}
+
+fn burst_on_doors(
+    mut commands: Commands,
+    mut doors: EventReader<DoorOpened>,
+    config: Res<EffectsConfig>,
+    settings: Res<Settings>,
+) {
+    for door in doors.read() {
+        let mut transform: Transform = door.coordinate.clone().into();
+        transform.translation.z = EFFECTS_Z;
+        let track = if settings.reduced_motion {
+            TweenTrack::step(
+                TweenTarget::SpriteAlpha(1.0, 0.0),
+                config.door_burst_duration,
+                EaseFunction::Linear,
+            )
+        } else {
+            TweenTrack::burst(config.door_burst_duration)
+        };
+        commands.spawn((
+            MySprite,
+            Name::new("Door Burst"),
+            door.sprite.clone(),
+            transform,
+            Tween::new(track),
+            DespawnAfterTween,
+        ));
+    }
+}
+
+fn unshake_camera(mut cameras: Query<(&mut Transform, &mut CameraShake)>) {
+    for (mut transform, mut shake) in cameras.iter_mut() {
+        transform.translation -= shake.offset;
+        shake.offset = Vec3::ZERO;
+    }
+}
+
+fn shake_camera(
+    mut commands: Commands,
+    mut cameras: Query<(Entity, &mut Transform, &mut CameraShake)>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, mut shake) in cameras.iter_mut() {
+        shake.timer.tick(time.delta());
+        if shake.timer.finished() {
+            commands.entity(entity).remove::<CameraShake>();
+            continue;
+        }
+        // fades out, two detuned waves look random enough
+        let t = shake.timer.elapsed_secs();
+        let strength = shake.strength * (1.0 - shake.timer.fraction());
+        shake.offset = Vec3::new((t * 97.0).sin(), (t * 71.0).cos(), 0.0) * strength;
+        transform.translation += shake.offset;
+    }
+}
+
+fn stop_effects(mut commands: Commands, cameras: Query<Entity, With<CameraShake>>) {
+    debug!("stopping {}", NAME);
+    for camera in cameras.iter() {
+        commands.entity(camera).remove::<CameraShake>();
+    }
+}
+
+// helper functions
+
+// tests
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    fn setup(reduced_motion: bool) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_event::<MoveBlocked>();
+        app.init_resource::<EffectsConfig>();
+        app.insert_resource(Settings {
+            reduced_motion,
+            ..default()
+        });
+        app.add_systems(Update, bump_on_blocked);
+        let player = app
+            .world_mut()
+            .spawn((Transform::default(), TileCoordinate::default()))
+            .id();
+        let wall = app
+            .world_mut()
+            .spawn(TileCoordinate { x: 1, y: 0, z: 0 })
+            .id();
+        app.world_mut().spawn(GameCamera);
+        app.world_mut().send_event(MoveBlocked {
+            mover: player,
+            blocked_by: wall,
+        });
+        (app, player)
+    }
+
+    #[test]
+    fn should_bump_and_shake_on_blocked_move() {
+        // given
+        let (mut app, player) = setup(false);
+
+        // when
+        app.update();
+
+        // then
+        let tween = app.world().get::<Tween>(player).unwrap();
+        assert_eq!(tween.duration(), EffectsConfig::default().shake_duration);
+        let mut shakes = app.world_mut().query::<&CameraShake>();
+        assert_eq!(shakes.iter(app.world()).count(), 1);
+    }
+
+    #[test]
+    fn should_skip_motion_effects_when_reduced() {
+        // given
+        let (mut app, player) = setup(true);
+
+        // when
+        app.update();
+
+        // then
+        assert!(app.world().get::<Tween>(player).is_none());
+        let mut shakes = app.world_mut().query::<&CameraShake>();
+        assert_eq!(shakes.iter(app.world()).count(), 0);
+    }
+}
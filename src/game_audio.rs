@@ -0,0 +1,355 @@
+use bevy::{
+    app::Plugin,
+    asset::{AssetLoader, LoadContext, io::Reader},
+    audio::Volume,
+    platform::collections::HashMap,
+};
+
+use AppState::Running;
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    app_states::{AppState, LevelState},
+    gold::PlayerPickedUpGoldCoins,
+    health::{PickedUpEmptyHeart, PickedUpHearts},
+    interaction::Interacted,
+    movement::{MoveBlocked, MoveTriggered},
+    settings::Settings,
+};
+
+// Constants
+const NAME: &str = "game_audio";
+const SOUND_BANK_FILE: &str = "audio/sounds.bank.ron";
+const CROSSFADE_SECONDS: f32 = 1.5;
+
+// Plugin
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // assets
+            .init_asset::<SoundBank>()
+            .init_asset_loader::<SoundBankLoader>()
+            // events
+            .add_event::<PlaySound>()
+            // systems
+            .add_systems(Startup, load_sound_bank)
+            .add_systems(Update, (dispatch_sounds).run_if(in_state(Running)))
+            .add_systems(
+                Update,
+                (play_sounds, switch_music, fade_music)
+                    .chain()
+                    .after(dispatch_sounds)
+                    .run_if(resource_exists::<ActiveSoundBank>),
+            );
+    }
+}
+
+// Assets
+/// maps sound effects and level music to audio files, loaded from `*.bank.ron` files
+#[derive(Asset, TypePath, Debug, Default)]
+pub struct SoundBank {
+    pub effects: HashMap<SoundEffect, EffectSound>,
+    pub music: HashMap<LevelState, Handle<AudioSource>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EffectSound {
+    pub source: Handle<AudioSource>,
+    pub volume: f32,
+}
+
+#[derive(Deserialize)]
+struct SoundBankFile {
+    #[serde(default)]
+    effects: HashMap<SoundEffect, EffectSoundFile>,
+    #[serde(default)]
+    music: HashMap<LevelState, String>,
+}
+
+#[derive(Deserialize)]
+struct EffectSoundFile {
+    file: String,
+    #[serde(default = "full_volume")]
+    volume: f32,
+}
+
+#[derive(Default)]
+pub struct SoundBankLoader;
+
+#[derive(Debug, Error)]
+pub enum SoundBankLoaderError {
+    #[error("could not read sound bank: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse sound bank: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for SoundBankLoader {
+    type Asset = SoundBank;
+    type Settings = ();
+    type Error = SoundBankLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file = ron::de::from_bytes::<SoundBankFile>(&bytes)?;
+        Ok(SoundBank {
+            effects: file
+                .effects
+                .into_iter()
+                .map(|(effect, sound)| {
+                    let source = load_context.load(sound.file);
+                    (
+                        effect,
+                        EffectSound {
+                            source,
+                            volume: sound.volume,
+                        },
+                    )
+                })
+                .collect(),
+            music: file
+                .music
+                .into_iter()
+                .map(|(level, file)| (level, load_context.load(file)))
+                .collect(),
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bank.ron"]
+    }
+}
+
+// Components
+/// looping level music, fades in when spawned and out when replaced
+#[derive(Component)]
+struct Music {
+    track: Handle<AudioSource>,
+    volume: f32,
+    fading_out: bool,
+}
+
+// Resources
+#[derive(Resource)]
+pub struct ActiveSoundBank(pub Handle<SoundBank>);
+
+// Events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum SoundEffect {
+    Move,
+    Blocked,
+    Gold,
+    Heart,
+    EmptyHeart,
+    Interact,
+}
+
+/// plays a sound effect from the active sound bank
+#[derive(Event, Debug, PartialEq, Eq)]
+pub struct PlaySound(pub SoundEffect);
+
+// Systems
+fn load_sound_bank(mut commands: Commands, asset_server: Res<AssetServer>) {
+    debug!("starting {}", NAME);
+    commands.insert_resource(ActiveSoundBank(asset_server.load(SOUND_BANK_FILE)));
+}
+
+fn dispatch_sounds(
+    mut moved: EventReader<MoveTriggered>,
+    mut blocked: EventReader<MoveBlocked>,
+    mut gold: EventReader<PlayerPickedUpGoldCoins>,
+    mut hearts: EventReader<PickedUpHearts>,
+    mut empty_hearts: EventReader<PickedUpEmptyHeart>,
+    mut interacted: EventReader<Interacted>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    let effects = [
+        (SoundEffect::Move, moved.read().count()),
+        (SoundEffect::Blocked, blocked.read().count()),
+        (SoundEffect::Gold, gold.read().count()),
+        (SoundEffect::Heart, hearts.read().count()),
+        (SoundEffect::EmptyHeart, empty_hearts.read().count()),
+        (SoundEffect::Interact, interacted.read().count()),
+    ];
+    // several events of a kind in one frame would only stack up to a louder sound
+    for (effect, count) in effects {
+        if count > 0 {
+            sounds.write(PlaySound(effect));
+        }
+    }
+}
+
+fn play_sounds(
+    mut commands: Commands,
+    mut sounds: EventReader<PlaySound>,
+    active: Res<ActiveSoundBank>,
+    banks: Res<Assets<SoundBank>>,
+    settings: Res<Settings>,
+) {
+    let Some(bank) = banks.get(&active.0) else {
+        sounds.clear();
+        return;
+    };
+    for sound in sounds.read() {
+        let Some(effect) = bank.effects.get(&sound.0) else {
+            debug!("no sound for {:?}", sound.0);
+            continue;
+        };
+        // the master volume is applied by the global volume
+        commands.spawn((
+            AudioPlayer(effect.source.clone()),
+            PlaybackSettings::DESPAWN
+                .with_volume(Volume::Linear(effect.volume * settings.effects_volume)),
+        ));
+    }
+}
+
+fn switch_music(
+    mut commands: Commands,
+    active: Res<ActiveSoundBank>,
+    banks: Res<Assets<SoundBank>>,
+    level: Option<Res<State<LevelState>>>,
+    mut music: Query<&mut Music>,
+) {
+    let Some(bank) = banks.get(&active.0) else {
+        return;
+    };
+    // outside of a level the music fades out
+    let track = level.and_then(|level| bank.music.get(level.get()));
+    let playing = music
+        .iter()
+        .any(|m| !m.fading_out && Some(&m.track) == track);
+    if playing {
+        return;
+    }
+    for mut m in music.iter_mut() {
+        m.fading_out = true;
+    }
+    if let Some(track) = track {
+        debug!("switching music to {:?}", track);
+        commands.spawn((
+            Music {
+                track: track.clone(),
+                volume: 0.0,
+                fading_out: false,
+            },
+            AudioPlayer(track.clone()),
+            PlaybackSettings::LOOP.with_volume(Volume::Linear(0.0)),
+        ));
+    }
+}
+
+fn fade_music(
+    mut commands: Commands,
+    mut music: Query<(Entity, &mut Music, Option<&mut AudioSink>)>,
+    settings: Res<Settings>,
+    time: Res<Time<Real>>,
+) {
+    // real time keeps fading while the game is paused
+    let step = time.delta_secs() / CROSSFADE_SECONDS;
+    for (entity, mut m, sink) in music.iter_mut() {
+        if m.fading_out {
+            m.volume = (m.volume - step).max(0.0);
+            if m.volume == 0.0 {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        } else {
+            m.volume = (m.volume + step).min(1.0);
+        }
+        if let Some(mut sink) = sink {
+            sink.set_volume(Volume::Linear(m.volume * settings.master_volume));
+        }
+    }
+}
+
+// helper functions
+fn full_volume() -> f32 {
+    1.0
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn should_map_gameplay_events_to_sounds() {
+        // given
+        let mut app = App::new();
+        app.add_event::<MoveTriggered>()
+            .add_event::<MoveBlocked>()
+            .add_event::<PlayerPickedUpGoldCoins>()
+            .add_event::<PickedUpHearts>()
+            .add_event::<PickedUpEmptyHeart>()
+            .add_event::<Interacted>()
+            .add_event::<PlaySound>();
+        app.add_systems(Update, dispatch_sounds);
+        let entity = app.world_mut().spawn_empty().id();
+
+        // when
+        app.world_mut().send_event(MoveBlocked {
+            mover: entity,
+            blocked_by: entity,
+        });
+        app.world_mut().send_event(Interacted(entity));
+        app.world_mut().send_event(Interacted(entity));
+        app.update();
+
+        // then
+        let events = app.world().resource::<Events<PlaySound>>();
+        let sounds: Vec<&PlaySound> = events.iter_current_update_events().collect();
+        assert_eq!(
+            sounds,
+            vec![
+                &PlaySound(SoundEffect::Blocked),
+                &PlaySound(SoundEffect::Interact)
+            ]
+        );
+    }
+
+    #[test]
+    fn should_play_sounds_at_effects_volume_without_audio_output() {
+        // given
+        let mut app = App::new();
+        app.add_event::<PlaySound>();
+        app.insert_resource(Settings {
+            effects_volume: 0.5,
+            ..default()
+        });
+        let mut banks = Assets::<SoundBank>::default();
+        let mut bank = SoundBank::default();
+        bank.effects.insert(
+            SoundEffect::Gold,
+            EffectSound {
+                source: Handle::default(),
+                volume: 0.8,
+            },
+        );
+        app.insert_resource(ActiveSoundBank(banks.add(bank)));
+        app.insert_resource(banks);
+        app.add_systems(Update, play_sounds);
+
+        // when
+        app.world_mut().send_event(PlaySound(SoundEffect::Gold));
+        app.world_mut().send_event(PlaySound(SoundEffect::Move));
+        app.update();
+
+        // then
+        let mut players = app.world_mut().query::<(&AudioPlayer, &PlaybackSettings)>();
+        let settings: Vec<&PlaybackSettings> = players.iter(app.world()).map(|(_, s)| s).collect();
+        assert_eq!(settings.len(), 1);
+        assert_eq!(settings[0].volume.to_linear(), 0.4);
+    }
+}
@@ -1,19 +1,37 @@
-use bevy::app::Plugin;
+use bevy::{app::Plugin, platform::collections::HashSet};
 
-use crate::{app_states::AppState, controls::PlayerControlled};
+use crate::{
+    app_states::AppState,
+    controls::PlayerControlled,
+    movement::MoveTriggered,
+    settings::Settings,
+    sprites::{MySprite, SPRITE_DIM, SPRITE_SCALE},
+    teleport::Teleported,
+    tiles::TileCoordinate,
+    tween::Tween,
+};
 use AppState::Running;
 use bevy::prelude::*;
 
 // Constants
 const NAME: &str = "game_camera";
 
+/// world size of one tile
+const TILE_SIZE: f32 = SPRITE_DIM as f32 * SPRITE_SCALE;
+
 // Plugin
 pub struct GameCameraPlugin;
 
 impl Plugin for GameCameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(Running), start_game_camera)
-            .add_systems(Update, (follow_player).run_if(in_state(Running)))
+        app.init_resource::<CameraConfig>()
+            .add_systems(OnEnter(Running), start_game_camera)
+            .add_systems(
+                Update,
+                (look_ahead, follow_players)
+                    .chain()
+                    .run_if(in_state(Running)),
+            )
             .add_systems(OnExit(Running), stop_game_camera);
     }
 }
@@ -22,7 +40,40 @@ impl Plugin for GameCameraPlugin {
 #[derive(Component)]
 pub struct GameCamera;
 
+/// offset toward the last move direction, eases back while standing still
+#[derive(Component, Debug, Default)]
+struct LookAhead(Vec2);
+
 // Resources
+/// how the game camera frames the players and the level
+#[derive(Resource, Debug, Clone)]
+pub struct CameraConfig {
+    /// half size of the rectangle around the camera center, in pixels, players move freely in it
+    pub dead_zone: Vec2,
+    /// in tiles, toward the last move direction
+    pub look_ahead: f32,
+    /// keeps the void around the level out of view
+    pub clamp_to_level: bool,
+    /// zooms in on levels smaller than the screen
+    pub zoom_to_fit: bool,
+    /// most zoom-to-fit may add on top of the zoom setting
+    pub max_fit_zoom: f32,
+    /// in tiles, kept around all players when several are framed
+    pub frame_padding: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        CameraConfig {
+            dead_zone: Vec2::new(48.0, 32.0),
+            look_ahead: 1.0,
+            clamp_to_level: true,
+            zoom_to_fit: true,
+            max_fit_zoom: 2.0,
+            frame_padding: 2.0,
+        }
+    }
+}
 
 // Events
 
@@ -30,58 +81,288 @@ pub struct GameCamera;
 fn start_game_camera(mut commands: Commands, cameras: Query<Entity, With<Camera2d>>) {
     debug!("starting {}", NAME);
     if let Ok(camera) = cameras.single() {
-        commands.entity(camera).insert(GameCamera);
+        commands
+            .entity(camera)
+            .insert((GameCamera, LookAhead::default()));
+    }
+}
+
+fn look_ahead(
+    mut moves: EventReader<MoveTriggered>,
+    players: Query<(), With<PlayerControlled>>,
+    mut cameras: Query<&mut LookAhead, With<GameCamera>>,
+    config: Res<CameraConfig>,
+    settings: Res<Settings>,
+) {
+    let Ok(mut look_ahead) = cameras.single_mut() else {
+        moves.clear();
+        return;
+    };
+    let mut direction = None;
+    for m in moves.read().filter(|m| players.contains(m.mover)) {
+        direction = Some(Vec2::new(
+            (m.end.x - m.start.x) as f32,
+            (m.end.y - m.start.y) as f32,
+        ));
+    }
+    match direction {
+        Some(direction) => {
+            look_ahead.0 = direction.normalize_or_zero() * config.look_ahead * TILE_SIZE
+        }
+        None => look_ahead.0 *= 1.0 - settings.camera_follow * 0.25,
     }
 }
 
-fn follow_player(
-    players: Query<&GlobalTransform, (With<PlayerControlled>, Without<GameCamera>)>,
-    mut cameras: Query<&mut Transform, (With<GameCamera>, Without<PlayerControlled>)>,
+/// cuts to teleported players, they are framed by their tile until the teleport animation ends
+#[allow(clippy::too_many_arguments)]
+fn follow_players(
+    players: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            Option<&TileCoordinate>,
+            Has<Tween>,
+        ),
+        (With<PlayerControlled>, Without<GameCamera>),
+    >,
+    mut teleports: EventReader<Teleported>,
+    mut teleporting: Local<HashSet<Entity>>,
+    mut cameras: Query<
+        (&mut Transform, &mut Projection, &Camera, &LookAhead),
+        (With<GameCamera>, Without<PlayerControlled>),
+    >,
+    tiles: Query<&TileCoordinate, With<MySprite>>,
+    config: Res<CameraConfig>,
+    settings: Res<Settings>,
 ) {
     debug!("updating {}", NAME);
-    // TODO: assumption: one player, one camera, for now
-    // otherwise we would have to calculate an average and a zoom level to capture all player
-    // controlled entities
-    if let Ok(player) = players.single()
-        && let Ok(mut camera) = cameras.single_mut()
+    let Ok((mut camera, mut projection, view, look_ahead)) = cameras.single_mut() else {
+        return;
+    };
+    let Projection::Orthographic(ortho) = projection.as_mut() else {
+        return;
+    };
+    let mut snap = false;
+    for t in teleports.read().filter(|t| players.contains(t.mover)) {
+        teleporting.insert(t.mover);
+        snap = true;
+    }
+    teleporting.retain(|e| players.get(*e).is_ok_and(|(_, _, _, tweened)| tweened));
+    let positions: Vec<Vec2> = players
+        .iter()
+        .map(|(e, transform, coordinate, _)| match coordinate {
+            Some(c) if teleporting.contains(&e) => Vec3::from(c.clone()).truncate(),
+            _ => transform.translation().truncate(),
+        })
+        .collect();
+    let Some(players_rect) = bounding_rect(positions.iter().copied()) else {
+        return;
+    };
+    let viewport = view.logical_viewport_size().unwrap_or(Vec2::ONE);
+    let level = if config.clamp_to_level || config.zoom_to_fit {
+        bounding_rect(tiles.iter().map(|c| Vec3::from(c.clone()).truncate()))
+            .map(|rect| rect.inflate(TILE_SIZE / 2.0))
+    } else {
+        None
+    };
+
+    // zoom
+    let base_scale = 1.0 / settings.zoom;
+    let mut scale = base_scale;
+    if config.zoom_to_fit
+        && let Some(level) = level
+    {
+        scale = scale
+            .min(fit_scale(level.size(), viewport))
+            .max(base_scale / config.max_fit_zoom);
+    }
+    if positions.len() > 1 {
+        let padding = 2.0 * config.frame_padding * TILE_SIZE;
+        scale = scale.max(fit_scale(players_rect.size() + padding, viewport));
+    }
+    ortho.scale += (scale - ortho.scale) * settings.camera_follow;
+
+    // position
+    let current = camera.translation.truncate();
+    let target = players_rect.center() + look_ahead.0;
+    let outside = target - current;
+    let excess = outside.signum() * (outside.abs() - config.dead_zone).max(Vec2::ZERO);
+    let mut next = if snap {
+        target
+    } else {
+        current + excess * settings.camera_follow
+    };
+    if config.clamp_to_level
+        && let Some(level) = level
     {
-        let move_dir = player.translation() - camera.translation;
-        camera.translation.x += move_dir.x * 0.125;
-        camera.translation.y += move_dir.y * 0.125;
+        next = clamp_to_level(next, viewport * ortho.scale / 2.0, level);
     }
+    camera.translation.x = next.x;
+    camera.translation.y = next.y;
 }
 
 fn stop_game_camera(mut commands: Commands, cameras: Query<Entity, With<GameCamera>>) {
     debug!("stopping {}", NAME);
     if let Ok(camera) = cameras.single() {
-        commands.entity(camera).remove::<GameCamera>();
+        commands.entity(camera).remove::<(GameCamera, LookAhead)>();
     }
 }
 
 // helper functions
+fn bounding_rect(points: impl Iterator<Item = Vec2>) -> Option<Rect> {
+    points.fold(None, |rect: Option<Rect>, p| {
+        Some(rect.map_or(Rect::from_corners(p, p), |r| r.union_point(p)))
+    })
+}
+
+/// projection scale that shows exactly the given world size
+fn fit_scale(size: Vec2, viewport: Vec2) -> f32 {
+    (size / viewport).max_element()
+}
+
+/// keeps the view inside the level, levels smaller than the view are centered
+fn clamp_to_level(center: Vec2, half_view: Vec2, level: Rect) -> Vec2 {
+    let min = level.min + half_view;
+    let max = level.max - half_view;
+    Vec2::new(
+        if min.x > max.x {
+            level.center().x
+        } else {
+            center.x.clamp(min.x, max.x)
+        },
+        if min.y > max.y {
+            level.center().y
+        } else {
+            center.y.clamp(min.y, max.y)
+        },
+    )
+}
 
 // tests
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
+    use crate::{controls::PlayerId, tween::TweenTrack};
+
     // Note this useful idiom: importing names from outer (for mod tests) scope.
-    //use super::*;
+    use super::*;
+
+    #[test]
+    fn should_clamp_view_to_level_and_center_small_levels() {
+        // given
+        let level = Rect::new(0.0, 0.0, 1000.0, 200.0);
+        let half_view = Vec2::new(200.0, 150.0);
+
+        // when
+        let at_the_edge = clamp_to_level(Vec2::new(50.0, 100.0), half_view, level);
+        let inside = clamp_to_level(Vec2::new(500.0, 0.0), half_view, level);
+
+        // then
+        assert_eq!(at_the_edge, Vec2::new(200.0, 100.0));
+        assert_eq!(inside, Vec2::new(500.0, 100.0));
+    }
+
+    #[test]
+    fn should_zoom_out_to_frame_all_players() {
+        // given
+        let mut app = App::new();
+        app.add_event::<MoveTriggered>().add_event::<Teleported>();
+        app.insert_resource(CameraConfig {
+            clamp_to_level: false,
+            zoom_to_fit: false,
+            frame_padding: 0.0,
+            ..default()
+        });
+        app.insert_resource(Settings {
+            camera_follow: 1.0,
+            ..default()
+        });
+        app.add_systems(Update, (look_ahead, follow_players).chain());
+        for (id, x) in [(PlayerId::One, -400.0), (PlayerId::Two, 1200.0)] {
+            app.world_mut().spawn((
+                PlayerControlled(id),
+                GlobalTransform::from_xyz(x, 100.0, 0.0),
+            ));
+        }
+        let camera = app
+            .world_mut()
+            .spawn((
+                GameCamera,
+                LookAhead::default(),
+                Camera::default(),
+                Projection::Orthographic(OrthographicProjection::default_2d()),
+                Transform::default(),
+            ))
+            .id();
+
+        // when
+        app.update();
+
+        // then
+        let transform = app.world().get::<Transform>(camera).unwrap();
+        assert_eq!(
+            transform.translation.truncate(),
+            Vec2::new(400.0 - 48.0, 68.0)
+        );
+        let Projection::Orthographic(ortho) = app.world().get::<Projection>(camera).unwrap() else {
+            panic!("expected an orthographic projection");
+        };
+        // without a render target the viewport is a single pixel
+        assert_eq!(ortho.scale, 1600.0);
+    }
 
-    /*
     #[test]
-    fn should_test_something() {
+    fn should_snap_to_teleported_players() {
         // given
-        //let mut _app = App::new();
+        let mut app = App::new();
+        app.add_event::<MoveTriggered>().add_event::<Teleported>();
+        app.insert_resource(CameraConfig {
+            clamp_to_level: false,
+            zoom_to_fit: false,
+            ..default()
+        });
+        app.insert_resource(Settings {
+            camera_follow: 0.1,
+            ..default()
+        });
+        app.add_systems(Update, (look_ahead, follow_players).chain());
+        let exit = TileCoordinate::new(40, -8);
+        // the teleport animation still shows the player on the entry
+        let player = app
+            .world_mut()
+            .spawn((
+                PlayerControlled(PlayerId::One),
+                GlobalTransform::default(),
+                exit.clone(),
+                Tween::new(TweenTrack::pulse(Duration::from_millis(200))),
+            ))
+            .id();
+        let camera = app
+            .world_mut()
+            .spawn((
+                GameCamera,
+                LookAhead::default(),
+                Camera::default(),
+                Projection::Orthographic(OrthographicProjection::default_2d()),
+                Transform::default(),
+            ))
+            .id();
 
         // when
-        //app.add_event::<HealthDamageReceived>();
-        //app.add_systems(Update, damage_received_listener);
-        //let entity = app.borrow_mut().world.spawn(Health(100)).id();
-        //app.borrow_mut().world.resource_mut::<Events<HealthDamageReceived>>().send(HealthDamageReceived { entity, damage: 10 });
-        //app.update();
+        app.world_mut().send_event(Teleported {
+            mover: player,
+            from: TileCoordinate::new(0, 0),
+            to: exit.clone(),
+        });
+        app.update();
+        app.update();
 
         // then
-        //assert!(app.world.get::<Health>(entity).is_some());
-        //assert_eq!(app.world.get::<Health>(entity).unwrap().0, 90);
+        let transform = app.world().get::<Transform>(camera).unwrap();
+        assert_eq!(
+            transform.translation.truncate(),
+            Vec3::from(exit).truncate()
+        );
     }
-    */
 }
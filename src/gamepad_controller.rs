@@ -0,0 +1,76 @@
+use bevy::app::Plugin;
+
+use AppState::Running;
+use bevy::prelude::*;
+
+use crate::{
+    app_states::AppState,
+    controls::{MoveIntent, PlayerId, controls_unlocked, move_intents},
+    movement::MoveRules,
+    tiles::Direction,
+};
+
+// Constants
+const NAME: &str = "gamepad";
+
+// Plugin
+pub struct GamepadControllerPlugin;
+
+impl Plugin for GamepadControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(Running), start_gamepad_controls)
+            .add_systems(
+                Update,
+                (update_gamepad_controls)
+                    .run_if(in_state(Running))
+                    .run_if(controls_unlocked),
+            )
+            .add_systems(OnExit(Running), stop_gamepad_controls);
+    }
+}
+
+// Components
+
+// Resources
+
+// Events
+
+// Systems
+fn start_gamepad_controls(mut _commands: Commands) {
+    debug!("starting {}", NAME);
+}
+
+fn update_gamepad_controls(
+    gamepads: Query<(Entity, &Gamepad)>,
+    rules: Res<MoveRules>,
+    mut intents: EventWriter<MoveIntent>,
+) {
+    debug!("updating {}", NAME);
+
+    // gamepads are handed to the players in the order they were connected
+    let mut gamepads: Vec<(Entity, &Gamepad)> = gamepads.iter().collect();
+    gamepads.sort_by_key(|(entity, _)| *entity);
+
+    let buttons = [
+        (GamepadButton::DPadLeft, Direction::Left),
+        (GamepadButton::DPadRight, Direction::Right),
+        (GamepadButton::DPadUp, Direction::Up),
+        (GamepadButton::DPadDown, Direction::Down),
+    ];
+    for ((_, gamepad), player) in gamepads.into_iter().zip(PlayerId::ALL) {
+        let just_pressed: Vec<Direction> = buttons
+            .iter()
+            .filter(|(b, _)| gamepad.just_pressed(*b))
+            .map(|(_, d)| *d)
+            .collect();
+
+        for direction in move_intents(&just_pressed, rules.directions) {
+            debug!("sending {:?} intent for {:?}", direction, player);
+            intents.write(MoveIntent { player, direction });
+        }
+    }
+}
+
+fn stop_gamepad_controls(mut _commands: Commands) {
+    debug!("stopping {}", NAME);
+}
@@ -1,9 +1,14 @@
-use bevy::app::Plugin;
+use bevy::{app::Plugin, platform::collections::HashMap};
 
 use AppState::Running;
 use bevy::prelude::*;
 
-use crate::{app_states::AppState, controls::PlayerControlled, tiles::TileCoordinate};
+use crate::{
+    app_states::AppState,
+    controls::{PlayerControlled, PlayerId},
+    grid::{GridIndex, GridRole},
+    tiles::TileCoordinate,
+};
 
 // Constants
 const NAME: &str = "gold";
@@ -16,6 +21,7 @@ impl Plugin for GoldPlugin {
         app
             // events
             .add_event::<PlayerPickedUpGoldCoins>()
+            .add_event::<GoldChanged>()
             .add_event::<FinalPlayerGoldAmount>()
             // systems
             .add_systems(OnEnter(Running), start_gold)
@@ -45,6 +51,8 @@ pub struct Gold {
 #[derive(Resource, Default)]
 pub struct PlayerGold {
     pub coins: i64,
+    /// coins of each player, every player starts the next level with their own
+    pub players: HashMap<PlayerId, i64>,
 }
 
 // Events
@@ -54,6 +62,13 @@ pub struct PlayerPickedUpGoldCoins {
     pub coins: Gold,
 }
 
+/// gold given or taken without a pickup, e.g. paid in a dialogue
+#[derive(Event)]
+pub struct GoldChanged {
+    pub player: Entity,
+    pub coins: Gold,
+}
+
 #[derive(Event)]
 pub struct FinalPlayerGoldAmount {
     pub coins: Gold,
@@ -67,27 +82,37 @@ fn start_gold(mut commands: Commands) {
 
 fn add_gold_to_player(
     mut commands: Commands,
-    mut players: Query<Entity, Added<PlayerControlled>>,
+    mut players: Query<(Entity, &PlayerControlled), Added<PlayerControlled>>,
     player_gold: Res<PlayerGold>,
 ) {
-    for player in players.iter_mut() {
-        commands.entity(player).insert(Gold {
-            coins: player_gold.coins,
-        });
-        debug!("added {} gold to player {}", player_gold.coins, player);
+    for (player, controlled) in players.iter_mut() {
+        let coins = player_gold
+            .players
+            .get(&controlled.0)
+            .copied()
+            .unwrap_or_default();
+        commands.entity(player).insert(Gold { coins });
+        debug!("added {} gold to player {}", coins, player);
     }
 }
 
 fn check_for_gold(
     mut commands: Commands,
     mut players: Query<(&TileCoordinate, &mut Gold, Entity), With<PlayerControlled>>,
-    coins: Query<(&TileCoordinate, &Gold, Entity), Without<PlayerControlled>>,
+    coins: Query<&Gold, Without<PlayerControlled>>,
+    grid: Res<GridIndex>,
     mut event: EventWriter<PlayerPickedUpGoldCoins>,
 ) {
     debug!("checking gold {}", NAME);
+    // players sharing a cell: the first one gets the coins
+    let mut picked_up = Vec::new();
     for (player_coordinate, mut player_coins, player) in players.iter_mut() {
-        for (gold_coordinate, coins, gold) in coins.iter() {
-            if player_coordinate.eq2d(gold_coordinate) {
+        for gold in grid.with_role(player_coordinate.cell(), GridRole::Pickup) {
+            if picked_up.contains(&gold) {
+                continue;
+            }
+            if let Ok(coins) = coins.get(gold) {
+                picked_up.push(gold);
                 player_coins.coins += coins.coins;
                 event.write(PlayerPickedUpGoldCoins {
                     player,
@@ -113,12 +138,23 @@ fn log_player_gold(
     }
 }
 
+/// banks the coins in total and for the player they went to
 fn player_coins_to_the_bank(
-    mut events: EventReader<PlayerPickedUpGoldCoins>,
+    mut picked_up: EventReader<PlayerPickedUpGoldCoins>,
+    mut changed: EventReader<GoldChanged>,
+    players: Query<&PlayerControlled>,
     mut player_gold: ResMut<PlayerGold>,
 ) {
-    for event in events.read() {
-        player_gold.coins += event.coins.coins;
+    let picked_up = picked_up.read().map(|e| (e.player, e.coins));
+    let changed = changed.read().map(|e| {
+        debug!("player {} gold changed by {}", e.player, e.coins.coins);
+        (e.player, e.coins)
+    });
+    for (player, coins) in picked_up.chain(changed) {
+        player_gold.coins += coins.coins;
+        if let Ok(controlled) = players.get(player) {
+            *player_gold.players.entry(controlled.0).or_default() += coins.coins;
+        }
     }
 }
 
@@ -143,24 +179,42 @@ fn stop_gold(
 #[cfg(test)]
 mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
-    //use super::*;
+    use super::*;
 
-    /*
     #[test]
-    fn should_test_something() {
+    fn should_start_the_next_level_with_the_banked_coins() {
         // given
-        //let mut _app = App::new();
+        let mut app = App::new();
+        app.add_event::<PlayerPickedUpGoldCoins>()
+            .add_event::<GoldChanged>()
+            .init_resource::<PlayerGold>();
+        app.add_systems(
+            Update,
+            (add_gold_to_player, player_coins_to_the_bank).chain(),
+        );
+        let one = app.world_mut().spawn(PlayerControlled(PlayerId::One)).id();
+        let two = app.world_mut().spawn(PlayerControlled(PlayerId::Two)).id();
+        app.update();
+        app.world_mut().send_event(PlayerPickedUpGoldCoins {
+            player: one,
+            coins: Gold { coins: 5 },
+        });
+        app.world_mut().send_event(GoldChanged {
+            player: two,
+            coins: Gold { coins: 3 },
+        });
+        app.update();
 
         // when
-        //app.add_event::<HealthDamageReceived>();
-        //app.add_systems(Update, damage_received_listener);
-        //let entity = app.borrow_mut().world.spawn(Health(100)).id();
-        //app.borrow_mut().world.resource_mut::<Events<HealthDamageReceived>>().send(HealthDamageReceived { entity, damage: 10 });
-        //app.update();
+        app.world_mut().despawn(one);
+        app.world_mut().despawn(two);
+        let next_one = app.world_mut().spawn(PlayerControlled(PlayerId::One)).id();
+        let next_two = app.world_mut().spawn(PlayerControlled(PlayerId::Two)).id();
+        app.update();
 
         // then
-        //assert!(app.world.get::<Health>(entity).is_some());
-        //assert_eq!(app.world.get::<Health>(entity).unwrap().0, 90);
+        assert_eq!(app.world().resource::<PlayerGold>().coins, 8);
+        assert_eq!(app.world().get::<Gold>(next_one).unwrap().coins, 5);
+        assert_eq!(app.world().get::<Gold>(next_two).unwrap().coins, 3);
     }
-    */
 }
@@ -0,0 +1,357 @@
+use bevy::{app::Plugin, platform::collections::HashMap};
+
+use bevy::prelude::*;
+
+use crate::{
+    controls::PlayerControlled,
+    gold::Gold,
+    health::{EmptyHeart, Hearts},
+    paths::{Hazard, Platform},
+    sprites::ExfilSprite,
+    teleport::Teleporter,
+    tiles::{FloorBehaviour, InteractableTile, Pit, SolidTile, TileCoordinate},
+};
+
+// Constants
+const NAME: &str = "grid";
+
+// Plugin
+pub struct GridPlugin;
+
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // resources
+            .init_resource::<GridIndex>()
+            // observers
+            .add_observer(coordinate_inserted)
+            .add_observer(coordinate_removed)
+            .add_observer(role_added::<SolidTile>)
+            .add_observer(role_removed::<SolidTile>)
+            .add_observer(role_added::<InteractableTile>)
+            .add_observer(role_removed::<InteractableTile>)
+            .add_observer(role_added::<ExfilSprite>)
+            .add_observer(role_removed::<ExfilSprite>)
+            .add_observer(role_added::<PlayerControlled>)
+            .add_observer(role_removed::<PlayerControlled>)
+            .add_observer(role_added::<Gold>)
+            .add_observer(role_removed::<Gold>)
+            .add_observer(role_added::<Hearts>)
+            .add_observer(role_removed::<Hearts>)
+            .add_observer(role_added::<EmptyHeart>)
+            .add_observer(role_removed::<EmptyHeart>)
+            .add_observer(role_added::<FloorBehaviour>)
+            .add_observer(role_removed::<FloorBehaviour>)
+            .add_observer(role_added::<Teleporter>)
+            .add_observer(role_removed::<Teleporter>)
+            .add_observer(role_added::<Hazard>)
+            .add_observer(role_removed::<Hazard>)
+            .add_observer(role_added::<Platform>)
+            .add_observer(role_removed::<Platform>)
+            .add_observer(role_added::<Pit>)
+            .add_observer(role_removed::<Pit>)
+            // systems
+            .add_systems(PostUpdate, sync_moved);
+    }
+}
+
+// Components
+/// what an entity on the grid is looked up for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GridRole {
+    Solid,
+    Interactable,
+    Exit,
+    Player,
+    /// gold, hearts and empty hearts, not the gold players carry
+    Pickup,
+    /// floors with a [FloorBehaviour]
+    Floor,
+    Teleporter,
+    /// saw blades and other things that hurt on contact
+    Hazard,
+    Platform,
+    Pit,
+}
+
+/// components that give their entity a role on the grid
+pub trait GridRoleComponent: Component {
+    const ROLE: GridRole;
+}
+
+impl GridRoleComponent for SolidTile {
+    const ROLE: GridRole = GridRole::Solid;
+}
+
+impl GridRoleComponent for InteractableTile {
+    const ROLE: GridRole = GridRole::Interactable;
+}
+
+impl GridRoleComponent for ExfilSprite {
+    const ROLE: GridRole = GridRole::Exit;
+}
+
+impl GridRoleComponent for PlayerControlled {
+    const ROLE: GridRole = GridRole::Player;
+}
+
+impl GridRoleComponent for Gold {
+    const ROLE: GridRole = GridRole::Pickup;
+}
+
+impl GridRoleComponent for Hearts {
+    const ROLE: GridRole = GridRole::Pickup;
+}
+
+impl GridRoleComponent for EmptyHeart {
+    const ROLE: GridRole = GridRole::Pickup;
+}
+
+impl GridRoleComponent for FloorBehaviour {
+    const ROLE: GridRole = GridRole::Floor;
+}
+
+impl GridRoleComponent for Teleporter {
+    const ROLE: GridRole = GridRole::Teleporter;
+}
+
+impl GridRoleComponent for Hazard {
+    const ROLE: GridRole = GridRole::Hazard;
+}
+
+impl GridRoleComponent for Platform {
+    const ROLE: GridRole = GridRole::Platform;
+}
+
+impl GridRoleComponent for Pit {
+    const ROLE: GridRole = GridRole::Pit;
+}
+
+// Resources
+/// every entity with a [TileCoordinate] by 2D cell, whatever layer it is drawn on.
+/// spawns and despawns are tracked by observers, moves are synced at the end of the frame.
+/// movers that need the index right away call [GridIndex::place] themselves.
+///
+/// entities on a cell are stacked: the highest z first, the latest placed first on the same z.
+/// the top entity with a role is the one that blocks or is interacted with.
+#[derive(Resource, Default, Debug)]
+pub struct GridIndex {
+    cells: HashMap<IVec2, Vec<Entity>>,
+    entries: HashMap<Entity, GridEntry>,
+}
+
+#[derive(Default, Debug)]
+struct GridEntry {
+    cell: Option<IVec2>,
+    z: i32,
+    roles: Vec<GridRole>,
+}
+
+impl GridIndex {
+    /// puts the entity on top of its layer on the cell, or moves it there
+    pub fn place(&mut self, entity: Entity, coordinate: &TileCoordinate) {
+        let cell = coordinate.cell();
+        let entry = self.entries.entry(entity).or_default();
+        if entry.cell == Some(cell) && entry.z == coordinate.z {
+            return;
+        }
+        entry.z = coordinate.z;
+        if let Some(old) = entry.cell.replace(cell) {
+            remove_from_cell(&mut self.cells, old, entity);
+        }
+        let entries = &self.entries;
+        let stack = self.cells.entry(cell).or_default();
+        let at = stack
+            .iter()
+            .position(|e| entries.get(e).is_none_or(|other| other.z <= coordinate.z))
+            .unwrap_or(stack.len());
+        stack.insert(at, entity);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(cell) = self.entries.remove(&entity).and_then(|entry| entry.cell) {
+            remove_from_cell(&mut self.cells, cell, entity);
+        }
+    }
+
+    pub fn add_role(&mut self, entity: Entity, role: GridRole) {
+        let entry = self.entries.entry(entity).or_default();
+        if !entry.roles.contains(&role) {
+            entry.roles.push(role);
+        }
+    }
+
+    pub fn remove_role(&mut self, entity: Entity, role: GridRole) {
+        if let Some(entry) = self.entries.get_mut(&entity) {
+            entry.roles.retain(|r| *r != role);
+        }
+    }
+
+    /// all entities on the cell, top first
+    pub fn at(&self, cell: IVec2) -> impl Iterator<Item = Entity> + '_ {
+        self.cells.get(&cell).into_iter().flatten().copied()
+    }
+
+    /// entities on the cell with the role, top first
+    pub fn with_role(&self, cell: IVec2, role: GridRole) -> impl Iterator<Item = Entity> + '_ {
+        self.at(cell).filter(move |entity| {
+            self.entries
+                .get(entity)
+                .is_some_and(|entry| entry.roles.contains(&role))
+        })
+    }
+
+    /// the top entity on the cell with the role
+    pub fn first(&self, cell: IVec2, role: GridRole) -> Option<Entity> {
+        self.with_role(cell, role).next()
+    }
+}
+
+// Events
+
+// Systems
+fn coordinate_inserted(
+    trigger: Trigger<OnInsert, TileCoordinate>,
+    coordinates: Query<&TileCoordinate>,
+    mut grid: ResMut<GridIndex>,
+) {
+    if let Ok(coordinate) = coordinates.get(trigger.target()) {
+        grid.place(trigger.target(), coordinate);
+    }
+}
+
+fn coordinate_removed(trigger: Trigger<OnRemove, TileCoordinate>, mut grid: ResMut<GridIndex>) {
+    debug!("removing {} entry of {}", NAME, trigger.target());
+    grid.remove(trigger.target());
+}
+
+fn role_added<C: GridRoleComponent>(
+    trigger: Trigger<OnInsert, C>,
+    players: Query<(), With<PlayerControlled>>,
+    mut grid: ResMut<GridIndex>,
+) {
+    let entity = trigger.target();
+    // players carry gold, they are no pickups
+    match C::ROLE {
+        GridRole::Pickup if players.contains(entity) => return,
+        GridRole::Player => grid.remove_role(entity, GridRole::Pickup),
+        _ => (),
+    }
+    grid.add_role(entity, C::ROLE);
+}
+
+fn role_removed<C: GridRoleComponent>(trigger: Trigger<OnRemove, C>, mut grid: ResMut<GridIndex>) {
+    grid.remove_role(trigger.target(), C::ROLE);
+}
+
+/// catches coordinates changed without [GridIndex::place]
+fn sync_moved(
+    moved: Query<(Entity, &TileCoordinate), Changed<TileCoordinate>>,
+    mut grid: ResMut<GridIndex>,
+) {
+    for (entity, coordinate) in moved.iter() {
+        grid.place(entity, coordinate);
+    }
+}
+
+// helper functions
+fn remove_from_cell(cells: &mut HashMap<IVec2, Vec<Entity>>, cell: IVec2, entity: Entity) {
+    if let Some(entities) = cells.get_mut(&cell) {
+        entities.retain(|e| *e != entity);
+        if entities.is_empty() {
+            cells.remove(&cell);
+        }
+    }
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use crate::{controls::PlayerId, tiles::TileLayer};
+
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn should_index_entities_by_cell_and_role() {
+        // given
+        let mut app = App::new();
+        app.add_plugins(GridPlugin);
+        let wall = app
+            .world_mut()
+            .spawn((SolidTile, TileCoordinate::new(1, 0)))
+            .id();
+        let heart = app
+            .world_mut()
+            .spawn((Hearts(1), TileCoordinate::new(1, 0).on(TileLayer::Overlay)))
+            .id();
+
+        // when
+        app.world_mut()
+            .entity_mut(heart)
+            .insert(TileCoordinate::new(2, 0));
+        app.world_mut().despawn(wall);
+
+        // then
+        let grid = app.world().resource::<GridIndex>();
+        assert_eq!(grid.at(IVec2::new(1, 0)).count(), 0);
+        assert_eq!(grid.first(IVec2::new(2, 0), GridRole::Pickup), Some(heart));
+        assert_eq!(grid.first(IVec2::new(2, 0), GridRole::Solid), None);
+        assert_eq!(grid.at(IVec2::new(2, 0)).collect::<Vec<_>>(), vec![heart]);
+    }
+
+    #[test]
+    fn should_sync_moved_players_without_making_them_pickups() {
+        // given
+        let mut app = App::new();
+        app.add_plugins(GridPlugin);
+        let player = app
+            .world_mut()
+            .spawn((PlayerControlled(PlayerId::One), TileCoordinate::new(0, 0)))
+            .id();
+        app.world_mut().entity_mut(player).insert(Gold { coins: 3 });
+
+        // when
+        app.world_mut().get_mut::<TileCoordinate>(player).unwrap().x = 1;
+        app.update();
+
+        // then
+        let grid = app.world().resource::<GridIndex>();
+        assert_eq!(grid.first(IVec2::new(1, 0), GridRole::Player), Some(player));
+        assert_eq!(grid.first(IVec2::new(1, 0), GridRole::Pickup), None);
+        assert_eq!(grid.at(IVec2::ZERO).count(), 0);
+    }
+
+    #[test]
+    fn should_stack_by_layer_then_latest_placed() {
+        // given
+        let mut app = App::new();
+        app.add_plugins(GridPlugin);
+        let cell = TileCoordinate::new(0, 0);
+        let npc = app
+            .world_mut()
+            .spawn((InteractableTile, cell.clone().on(TileLayer::Actors)))
+            .id();
+        let door = app
+            .world_mut()
+            .spawn((
+                InteractableTile,
+                SolidTile,
+                cell.clone().on(TileLayer::Objects),
+            ))
+            .id();
+
+        // when
+        let lever = app
+            .world_mut()
+            .spawn((InteractableTile, cell.clone().on(TileLayer::Objects)))
+            .id();
+
+        // then
+        let grid = app.world().resource::<GridIndex>();
+        let stack: Vec<Entity> = grid.at(IVec2::ZERO).collect();
+        assert_eq!(stack, vec![npc, lever, door]);
+        assert_eq!(grid.first(IVec2::ZERO, GridRole::Interactable), Some(npc));
+        assert_eq!(grid.first(IVec2::ZERO, GridRole::Solid), Some(door));
+    }
+}
@@ -3,7 +3,11 @@ use bevy::app::Plugin;
 use AppState::Running;
 use bevy::prelude::*;
 
-use crate::{app_states::AppState, tiles::TileCoordinate};
+use crate::{
+    app_states::AppState,
+    grid::{GridIndex, GridRole},
+    tiles::TileCoordinate,
+};
 
 // Constants
 const NAME: &str = "health";
@@ -17,11 +21,18 @@ impl Plugin for HealthPlugin {
             // events
             .add_event::<PickedUpHearts>()
             .add_event::<PickedUpEmptyHeart>()
+            .add_event::<HealthDamageReceived>()
             // systems
             .add_systems(OnEnter(Running), start_health)
             .add_systems(
                 Update,
-                (update_health, check_for_heart, check_for_empty_heart).run_if(in_state(Running)),
+                (
+                    update_health,
+                    check_for_heart,
+                    check_for_empty_heart,
+                    damage_received_listener,
+                )
+                    .run_if(in_state(Running)),
             )
             .add_systems(OnExit(Running), stop_health);
     }
@@ -59,6 +70,13 @@ pub struct PickedUpEmptyHeart {
     pub entity: Entity,
 }
 
+/// hearts lost, e.g. to a saw blade
+#[derive(Event)]
+pub struct HealthDamageReceived {
+    pub entity: Entity,
+    pub damage: usize,
+}
+
 // Systems
 fn start_health(mut _commands: Commands) {
     debug!("starting {}", NAME);
@@ -74,15 +92,20 @@ fn update_health(healths: Query<(Entity, &Health)>) {
 fn check_for_heart(
     mut commands: Commands,
     mut health_bearer: Query<(&TileCoordinate, &mut Health, Entity)>,
-    hearts: Query<(&TileCoordinate, &Hearts, Entity)>,
+    hearts: Query<&Hearts>,
+    grid: Res<GridIndex>,
     mut event: EventWriter<PickedUpHearts>,
 ) {
     debug!("checking hearts {}", NAME);
+    // bearers sharing a cell: the first one that can take the hearts gets them
+    let mut picked_up = Vec::new();
     for (health_coordinate, mut health, has_health) in health_bearer.iter_mut() {
-        for (heart_coordinate, hearts, heart) in hearts.iter() {
-            if health_coordinate.eq2d(heart_coordinate)
-                && health.hearts.0 + hearts.0 <= health.max.0
-            {
+        for heart in grid.with_role(health_coordinate.cell(), GridRole::Pickup) {
+            let Ok(hearts) = hearts.get(heart) else {
+                continue;
+            };
+            if !picked_up.contains(&heart) && health.hearts.0 + hearts.0 <= health.max.0 {
+                picked_up.push(heart);
                 health.hearts.0 += hearts.0;
                 event.write(PickedUpHearts {
                     entity: has_health,
@@ -97,13 +120,16 @@ fn check_for_heart(
 fn check_for_empty_heart(
     mut commands: Commands,
     mut health_bearer: Query<(&TileCoordinate, &mut Health, Entity)>,
-    hearts: Query<(&TileCoordinate, Entity), With<EmptyHeart>>,
+    hearts: Query<(), With<EmptyHeart>>,
+    grid: Res<GridIndex>,
     mut event: EventWriter<PickedUpEmptyHeart>,
 ) {
     debug!("checking hearts {}", NAME);
+    let mut picked_up = Vec::new();
     for (health_coordinate, mut health, has_health) in health_bearer.iter_mut() {
-        for (heart_coordinate, empty_heart) in hearts.iter() {
-            if health_coordinate.eq2d(heart_coordinate) {
+        for empty_heart in grid.with_role(health_coordinate.cell(), GridRole::Pickup) {
+            if hearts.contains(empty_heart) && !picked_up.contains(&empty_heart) {
+                picked_up.push(empty_heart);
                 health.max = Hearts(health.max.0 + 1);
                 event.write(PickedUpEmptyHeart { entity: has_health });
                 commands.entity(empty_heart).despawn();
@@ -112,6 +138,21 @@ fn check_for_empty_heart(
     }
 }
 
+fn damage_received_listener(
+    mut events: EventReader<HealthDamageReceived>,
+    mut health_bearer: Query<&mut Health>,
+) {
+    for event in events.read() {
+        if let Ok(mut health) = health_bearer.get_mut(event.entity) {
+            health.hearts.0 = health.hearts.0.saturating_sub(event.damage);
+            debug!(
+                "{} lost {} hearts: {:?}",
+                event.entity, event.damage, health
+            );
+        }
+    }
+}
+
 fn stop_health(mut _commands: Commands) {
     debug!("stopping {}", NAME);
 }
@@ -121,6 +162,8 @@ fn stop_health(mut _commands: Commands) {
 // tests
 #[cfg(test)]
 mod tests {
+    use crate::grid::GridPlugin;
+
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
 
@@ -128,6 +171,7 @@ mod tests {
     fn should_pickup_heart() {
         // given
         let mut app = App::new();
+        app.add_plugins(GridPlugin);
 
         // when
         app.add_event::<PickedUpHearts>();
@@ -157,6 +201,7 @@ mod tests {
     fn should_not_pickup_heart_at_max_health() {
         // given
         let mut app = App::new();
+        app.add_plugins(GridPlugin);
 
         // when
         app.add_event::<PickedUpHearts>();
@@ -186,6 +231,7 @@ mod tests {
     fn should_pickup_empty_heart_to_increase_max_health() {
         // given
         let mut app = App::new();
+        app.add_plugins(GridPlugin);
 
         // when
         app.add_event::<PickedUpHearts>();
@@ -210,4 +256,27 @@ mod tests {
         assert!(app.world().get::<Hearts>(empty_heart).is_none());
         assert_eq!(app.world().get::<Health>(entity).unwrap().max.0, 3);
     }
+
+    #[test]
+    fn should_lose_hearts_on_damage() {
+        // given
+        let mut app = App::new();
+        app.add_event::<HealthDamageReceived>();
+        app.add_systems(Update, damage_received_listener);
+        let entity = app
+            .world_mut()
+            .spawn(Health {
+                hearts: Hearts(1),
+                max: Hearts(3),
+            })
+            .id();
+
+        // when
+        app.world_mut()
+            .send_event(HealthDamageReceived { entity, damage: 2 });
+        app.update();
+
+        // then
+        assert_eq!(app.world().get::<Health>(entity).unwrap().hearts.0, 0);
+    }
 }
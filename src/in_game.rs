@@ -5,10 +5,10 @@ use bevy::prelude::*;
 use AppState::Running;
 
 use crate::{
-    app_states::AppState,
+    app_states::{AppState, LevelState},
     controls::PlayerControlled,
-    sprites::{MoveAnimation, SPRITE_DIM, SPRITE_SCALE},
     tiles::TileCoordinate,
+    tween::Tween,
 };
 
 // Constants
@@ -23,18 +23,10 @@ impl Plugin for InGamePlugin {
             // events
             .add_event::<LevelStarted>()
             .add_event::<LevelFinished>()
+            .add_event::<ExitReached>()
             // systems
             .add_systems(OnEnter(Running), start_in_game)
-            .add_systems(
-                Update,
-                (
-                    update_in_game,
-                    update_tile_coordinates,
-                    update_transforms,
-                    logging,
-                )
-                    .run_if(in_state(Running)),
-            )
+            .add_systems(Update, (update_in_game, logging).run_if(in_state(Running)))
             .add_systems(OnExit(Running), stop_in_game);
     }
 }
@@ -51,6 +43,10 @@ pub struct LevelStarted;
 #[derive(Event)]
 pub struct LevelFinished;
 
+/// a player stepped on the exit of a level
+#[derive(Event)]
+pub struct ExitReached(pub LevelState);
+
 // Systems
 fn start_in_game(mut _commands: Commands) {
     debug!("starting {}", NAME);
@@ -62,7 +58,7 @@ fn update_in_game() {
 
 fn logging(
     transforms: Query<
-        (Entity, &Transform, &TileCoordinate, Option<&MoveAnimation>),
+        (Entity, &Transform, &TileCoordinate, Option<&Tween>),
         With<PlayerControlled>,
     >,
 ) {
@@ -78,39 +74,6 @@ fn logging(
     }
 }
 
-fn update_transforms(mut animations: Query<(&mut Transform, &MoveAnimation)>) {
-    debug!("handling animation {}", NAME);
-
-    for (mut t, a) in animations.iter_mut() {
-        let start_t = Transform::from_scale(Vec3::splat(SPRITE_SCALE)).with_translation(Vec3::new(
-            SPRITE_SCALE * a.start.x as f32 * SPRITE_DIM as f32,
-            SPRITE_SCALE * a.start.y as f32 * SPRITE_DIM as f32,
-            0.0,
-        ));
-
-        let end_t = Transform::from_scale(Vec3::splat(SPRITE_SCALE)).with_translation(Vec3::new(
-            SPRITE_SCALE * a.end.x as f32 * SPRITE_DIM as f32,
-            SPRITE_SCALE * a.end.y as f32 * SPRITE_DIM as f32,
-            0.0,
-        ));
-        let direction = end_t.translation - start_t.translation;
-        let eased_fraction = a.function.sample(a.timer.fraction());
-        if let Some(fraction) = eased_fraction {
-            t.translation = start_t.translation + direction * fraction;
-        }
-    }
-}
-
-fn update_tile_coordinates(
-    mut animation_changes: Query<(&MoveAnimation, &mut TileCoordinate), Changed<MoveAnimation>>,
-) {
-    for (a, mut tc) in animation_changes.iter_mut() {
-        // TODO: just assign end_tile? throws a cannot be dereferenced currently
-        tc.x = a.end.x;
-        tc.y = a.end.y;
-    }
-}
-
 fn stop_in_game(mut _commands: Commands) {
     debug!("stopping {}", NAME);
 }
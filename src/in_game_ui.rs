@@ -1,16 +1,30 @@
+use std::time::Duration;
+
 use bevy::app::Plugin;
 
 use AppState::Running;
 use bevy::prelude::*;
 
 use crate::{
-    app_states::AppState, controls::PlayerControlled, gold::Gold, health::Health,
-    in_game::LevelStarted,
+    app_states::AppState,
+    atlas::{CatalogTile, TileCatalog},
+    controls::{PlayerControlled, PlayerId},
+    gold::Gold,
+    health::Health,
+    sprites::SpriteSheetTile,
+    tween::{Tween, TweenTrack},
 };
 
 // Constants
 const NAME: &str = "in_game_ui";
 
+/// pixels of a heart or a coin on the hud
+const ICON_SIZE: f32 = 32.0;
+/// seconds a heart shows half full while it fills up or empties
+const HEART_FLIP: f32 = 0.15;
+/// seconds per coin while the gold counts up or down
+const GOLD_COUNT_TICK: f32 = 0.03;
+
 // Plugin
 pub struct InGameUIPlugin;
 
@@ -19,16 +33,16 @@ impl Plugin for InGameUIPlugin {
         app.add_systems(OnEnter(Running), start_ingame_ui)
             .add_systems(
                 Update,
-                (level_started, added_health_to_player, added_gold_to_player)
-                    .run_if(in_state(Running)),
-            )
-            .add_systems(
-                Update,
-                (changed_health_of_player)
-                    .run_if(resource_exists::<HeartUIRoot>)
+                (
+                    added_player,
+                    show_player_huds,
+                    update_hud_hearts,
+                    update_heart_icons,
+                    update_hud_gold,
+                )
+                    .chain()
                     .run_if(in_state(Running)),
             )
-            .add_systems(Update, (changed_gold_of_player).run_if(in_state(Running)))
             .add_systems(OnExit(Running), stop_ingame_ui);
     }
 }
@@ -37,91 +51,178 @@ impl Plugin for InGameUIPlugin {
 #[derive(Component)]
 struct InGameUI;
 
+/// hud panel of one player. it stays while levels despawn their players and shows the next
+/// player with the same id.
+#[derive(Component)]
+struct PlayerHud(PlayerId);
+
+/// row of heart slots, one per heart the player can have
+#[derive(Component)]
+struct HudHearts {
+    id: PlayerId,
+    /// the player shown last, a new one is shown without animations
+    player: Option<Entity>,
+}
+
+#[derive(Component, Debug)]
+struct HudHeart {
+    slot: usize,
+    full: bool,
+    /// half full while it runs
+    flip: Option<Timer>,
+}
+
 #[derive(Component)]
-struct InGameUIGold;
+struct HudGold {
+    id: PlayerId,
+    player: Option<Entity>,
+    shown: i64,
+    tick: Timer,
+}
 
 // Resources
-#[derive(Resource, Clone, Copy)]
-struct HeartUIRoot(Entity);
 
 // Events
 
 // Systems
 fn start_ingame_ui() {}
 
-fn level_started(
+fn added_player(
     mut commands: Commands,
-    mut started: EventReader<LevelStarted>,
-    gold_ui: Query<Entity, With<InGameUIGold>>,
+    catalog: Option<Res<TileCatalog>>,
+    added_players: Query<&PlayerControlled, Added<PlayerControlled>>,
+    huds: Query<&PlayerHud>,
 ) {
-    for _ in started.read() {
-        for ui in gold_ui.iter() {
-            commands.entity(ui).despawn();
+    let mut spawned: Vec<PlayerId> = huds.iter().map(|h| h.0).collect();
+    for controlled in added_players.iter() {
+        if spawned.contains(&controlled.0) {
+            continue;
         }
+        debug!("player added ui {}", NAME);
+        spawned.push(controlled.0);
+        let coin = catalog
+            .as_deref()
+            .and_then(|c| c.get(&SpriteSheetTile::GoldCoin));
+        commands.spawn(player_hud(controlled.0, coin));
     }
 }
 
-fn added_health_to_player(
-    mut commands: Commands,
-    player_health_added: Query<(Entity, &Health), (With<PlayerControlled>, Added<Health>)>,
-    mut health_ui_res: Option<ResMut<HeartUIRoot>>,
+/// huds of players that are gone hide until the player is back
+fn show_player_huds(
+    players: Query<&PlayerControlled>,
+    mut huds: Query<(&PlayerHud, &mut Visibility)>,
 ) {
-    for (_player, health) in player_health_added.iter() {
-        debug!("player health added ui {}", NAME);
-        let hearts_root = commands.spawn(heart_ui_root(health)).id();
-        if let Some(res) = &mut health_ui_res {
-            commands.entity(res.0).despawn();
-            commands.remove_resource::<HeartUIRoot>();
-            commands.insert_resource(HeartUIRoot(hearts_root));
+    for (hud, mut visibility) in huds.iter_mut() {
+        let shown = if players.iter().any(|p| p.0 == hud.0) {
+            Visibility::Inherited
         } else {
-            commands.insert_resource(HeartUIRoot(hearts_root));
-        }
-        debug!("player health added ui {}", hearts_root);
+            Visibility::Hidden
+        };
+        visibility.set_if_neq(shown);
     }
 }
 
-fn changed_health_of_player(
+/// adds and removes heart slots with the max health, flips the slots that changed
+fn update_hud_hearts(
     mut commands: Commands,
-    player_health_changed: Query<(Entity, &Health), (With<PlayerControlled>, Changed<Health>)>,
-    health_ui_res: Res<HeartUIRoot>,
+    players: Query<(Entity, &PlayerControlled, &Health)>,
+    mut rows: Query<(Entity, &mut HudHearts, Option<&Children>)>,
+    mut slots: Query<&mut HudHeart>,
 ) {
-    for (_player, health) in player_health_changed.iter() {
-        let hearts_root = commands.spawn(heart_ui_root(health)).id();
-        commands.entity(health_ui_res.0).despawn();
-        commands.remove_resource::<HeartUIRoot>();
-        commands.insert_resource(HeartUIRoot(hearts_root));
-        debug!("player health changed ui {}", hearts_root);
+    for (row, mut hearts, children) in rows.iter_mut() {
+        let Some((player, _, health)) = players.iter().find(|(_, p, _)| p.0 == hearts.id) else {
+            continue;
+        };
+        let snap = hearts.player != Some(player);
+        hearts.player = Some(player);
+
+        let mut existing = 0;
+        for child in children.into_iter().flatten() {
+            let Ok(mut slot) = slots.get_mut(*child) else {
+                continue;
+            };
+            if slot.slot >= health.max.0 {
+                commands.entity(*child).despawn();
+                continue;
+            }
+            existing += 1;
+            let full = slot.slot < health.hearts.0;
+            if slot.full != full {
+                slot.full = full;
+                if !snap {
+                    slot.flip = Some(Timer::from_seconds(HEART_FLIP, TimerMode::Once));
+                    commands.entity(*child).insert(heart_pulse());
+                }
+            }
+        }
+        for slot in existing..health.max.0 {
+            let full = slot < health.hearts.0;
+            let mut heart = commands.spawn((
+                HudHeart {
+                    slot,
+                    full,
+                    flip: None,
+                },
+                icon_node(),
+                ImageNode::default(),
+                ChildOf(row),
+            ));
+            if !snap {
+                heart.insert(heart_pulse());
+            }
+        }
     }
 }
 
-fn added_gold_to_player(
-    mut commands: Commands,
-    player_gold_added: Query<(Entity, &Gold), (With<PlayerControlled>, Added<Gold>)>,
-    gold_ui: Query<Entity, With<InGameUIGold>>,
+fn update_heart_icons(
+    time: Res<Time>,
+    catalog: Option<Res<TileCatalog>>,
+    mut slots: Query<(&mut HudHeart, &mut ImageNode)>,
 ) {
-    for (_player, gold) in player_gold_added.iter() {
-        debug!("player gold added ui {}", NAME);
-        if gold.coins > 0 {
-            for ui in gold_ui.iter() {
-                commands.entity(ui).despawn();
-            }
-            let gold_root = commands.spawn(gold_ui_root(gold)).id();
-            debug!("player gold added ui {}", gold_root);
+    for (mut slot, mut image) in slots.iter_mut() {
+        let flipping = slot
+            .flip
+            .as_mut()
+            .is_some_and(|t| !t.tick(time.delta()).finished());
+        if !flipping {
+            slot.flip = None;
+        }
+        let tile = match (flipping, slot.full) {
+            (true, _) => SpriteSheetTile::HalfHeart,
+            (false, true) => SpriteSheetTile::FullHeart,
+            (false, false) => SpriteSheetTile::EmptyHeart,
+        };
+        let Some(tile) = catalog.as_deref().and_then(|c| c.get(&tile)) else {
+            continue;
+        };
+        if image.texture_atlas.as_ref().map(|a| a.index) != Some(tile.index) {
+            *image = icon(tile);
         }
     }
 }
 
-fn changed_gold_of_player(
-    mut commands: Commands,
-    player_gold_changed: Query<(Entity, &Gold), (With<PlayerControlled>, Changed<Gold>)>,
-    gold_ui: Query<Entity, With<InGameUIGold>>,
+/// counts the shown gold towards the player's gold, one coin per tick
+fn update_hud_gold(
+    time: Res<Time>,
+    players: Query<(Entity, &PlayerControlled, &Gold)>,
+    mut golds: Query<(&mut HudGold, &mut Text)>,
 ) {
-    for (_player, gold) in player_gold_changed.iter() {
-        for ui in gold_ui.iter() {
-            commands.entity(ui).despawn();
+    for (mut hud, mut text) in golds.iter_mut() {
+        let Some((player, _, gold)) = players.iter().find(|(_, p, _)| p.0 == hud.id) else {
+            continue;
+        };
+        if hud.player != Some(player) {
+            hud.player = Some(player);
+            hud.shown = gold.coins;
+        }
+        let steps = hud.tick.tick(time.delta()).times_finished_this_tick() as i64;
+        let missing = gold.coins - hud.shown;
+        hud.shown += missing.signum() * missing.abs().min(steps);
+
+        let shown = format!("{}", hud.shown);
+        if text.0 != shown {
+            text.0 = shown;
         }
-        let gold_root = commands.spawn(gold_ui_root(gold)).id();
-        debug!("player gold changed ui {}", gold_root);
     }
 }
 
@@ -130,65 +231,145 @@ fn stop_ingame_ui(mut commands: Commands, ui: Query<Entity, With<InGameUI>>) {
     for x in ui.iter() {
         commands.entity(x).despawn();
     }
-    commands.remove_resource::<HeartUIRoot>();
 }
 
 // helper functions
-fn heart_ui_root(health: &Health) -> impl Bundle + use<> {
+/// first player on the left, second player on the right
+fn player_hud(id: PlayerId, coin: Option<&CatalogTile>) -> impl Bundle + use<> {
+    let (left, right, align) = match id {
+        PlayerId::One => (Val::Px(16.0), Val::Auto, AlignItems::Start),
+        PlayerId::Two => (Val::Auto, Val::Px(16.0), AlignItems::End),
+    };
     (
         InGameUI,
+        PlayerHud(id),
         Node {
-            width: Val::Percent(100.0),
-            height: Val::Percent(100.0),
-            align_items: AlignItems::Start,
-            justify_content: JustifyContent::Center,
+            position_type: PositionType::Absolute,
+            top: Val::Px(16.0),
+            left,
+            right,
+            flex_direction: FlexDirection::Column,
+            align_items: align,
+            row_gap: Val::Px(4.0),
             ..default()
         },
-        children![(Text::new(format!(
-            "Health: {:?}/{:?}",
-            health.hearts.0, health.max.0
-        )),)],
+        children![
+            Text::new(format!("Player {}", id.number())),
+            (
+                HudHearts { id, player: None },
+                Node {
+                    column_gap: Val::Px(2.0),
+                    ..default()
+                },
+            ),
+            (
+                Node {
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(4.0),
+                    ..default()
+                },
+                children![
+                    (icon_node(), coin.map(icon).unwrap_or_default()),
+                    (
+                        HudGold {
+                            id,
+                            player: None,
+                            shown: 0,
+                            tick: Timer::from_seconds(GOLD_COUNT_TICK, TimerMode::Repeating),
+                        },
+                        Text::new("0"),
+                    ),
+                ],
+            ),
+        ],
     )
 }
 
-fn gold_ui_root(gold: &Gold) -> impl Bundle + use<> {
-    (
-        InGameUI,
-        InGameUIGold,
-        Node {
-            width: Val::Percent(100.0),
-            height: Val::Percent(100.0),
-            align_items: AlignItems::End,
-            justify_content: JustifyContent::Center,
-            ..default()
+fn icon_node() -> Node {
+    Node {
+        width: Val::Px(ICON_SIZE),
+        height: Val::Px(ICON_SIZE),
+        ..default()
+    }
+}
+
+fn icon(tile: &CatalogTile) -> ImageNode {
+    ImageNode::from_atlas_image(
+        tile.image.clone(),
+        TextureAtlas {
+            layout: tile.layout.clone(),
+            index: tile.index,
         },
-        children![(Text::new(format!("Gold: {}", gold.coins)),)],
     )
+    .with_color(tile.color)
+}
+
+fn heart_pulse() -> Tween {
+    Tween::new(TweenTrack::pulse(Duration::from_millis(200)))
 }
 
 // tests
 #[cfg(test)]
 mod tests {
+    use crate::health::Hearts;
+
     // Note this useful idiom: importing names from outer (for mod tests) scope.
-    //use super::*;
-    //use std::borrow::BorrowMut;
+    use super::*;
 
-    /*
     #[test]
-    fn should_test_something() {
+    fn should_keep_one_hud_for_respawned_players() {
         // given
-        //let mut app = App::new();
+        let mut app = App::new();
+        app.init_resource::<Time>();
+        app.add_systems(
+            Update,
+            (
+                added_player,
+                show_player_huds,
+                update_hud_hearts,
+                update_heart_icons,
+                update_hud_gold,
+            )
+                .chain(),
+        );
+        let player = app
+            .world_mut()
+            .spawn((
+                PlayerControlled(PlayerId::One),
+                Health {
+                    hearts: Hearts(2),
+                    max: Hearts(3),
+                },
+                Gold { coins: 5 },
+            ))
+            .id();
+        app.update();
+        app.update();
 
         // when
-        //app.add_event::<HealthDamageReceived>();
-        //app.add_systems(Update, damage_received_listener);
-        //let entity = app.borrow_mut().world().spawn(Health(100)).id();
-        //app.borrow_mut().world().resource_mut::<Events<HealthDamageReceived>>().send(HealthDamageReceived { entity, damage: 10 });
-        //app.update();
+        app.world_mut().despawn(player);
+        app.world_mut().spawn((
+            PlayerControlled(PlayerId::One),
+            Health {
+                hearts: Hearts(1),
+                max: Hearts(2),
+            },
+            Gold { coins: 7 },
+        ));
+        app.update();
+        app.update();
 
         // then
-        //assert!(app.world().get::<Health>(entity).is_some());
-        //assert_eq!(app.world().get::<Health>(entity).unwrap().0, 90);
+        let world = app.world_mut();
+        assert_eq!(world.query::<&PlayerHud>().iter(world).count(), 1);
+        let mut hearts: Vec<(usize, bool)> = world
+            .query::<&HudHeart>()
+            .iter(world)
+            .map(|h| (h.slot, h.full))
+            .collect();
+        hearts.sort();
+        assert_eq!(hearts, vec![(0, true), (1, false)]);
+        let gold = world.query::<(&HudGold, &Text)>().single(world).unwrap().1;
+        assert_eq!(gold.0, "7");
     }
-    */
 }
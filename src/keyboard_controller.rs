@@ -5,7 +5,10 @@ use bevy::prelude::*;
 
 use crate::{
     app_states::AppState,
-    controls::{Down, Left, Right, Up},
+    controls::{MoveIntent, PlayerId, controls_unlocked, move_intents},
+    movement::MoveRules,
+    settings::Settings,
+    tiles::Direction,
 };
 
 // Constants
@@ -17,7 +20,12 @@ pub struct KeyboardControllerPlugin;
 impl Plugin for KeyboardControllerPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(Running), start_keyboard_controls)
-            .add_systems(Update, (update_keyboard_controls).run_if(in_state(Running)))
+            .add_systems(
+                Update,
+                (update_keyboard_controls)
+                    .run_if(in_state(Running))
+                    .run_if(controls_unlocked),
+            )
             .add_systems(OnExit(Running), stop_keyboard_controls);
     }
 }
@@ -35,31 +43,50 @@ fn start_keyboard_controls(mut _commands: Commands) {
 
 fn update_keyboard_controls(
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut left: EventWriter<Left>,
-    mut right: EventWriter<Right>,
-    mut up: EventWriter<Up>,
-    mut down: EventWriter<Down>,
+    settings: Res<Settings>,
+    rules: Res<MoveRules>,
+    mut intents: EventWriter<MoveIntent>,
 ) {
     debug!("updating {}", NAME);
 
-    if keyboard_input.just_pressed(KeyCode::KeyA) {
-        debug!("sending left event");
-        left.write(Left);
-    }
-
-    if keyboard_input.just_pressed(KeyCode::KeyD) {
-        debug!("sending right event");
-        right.write(Right);
-    }
-
-    if keyboard_input.just_pressed(KeyCode::KeyW) {
-        debug!("sending up event");
-        up.write(Up);
-    }
-
-    if keyboard_input.just_pressed(KeyCode::KeyS) {
-        debug!("sending down event");
-        down.write(Down);
+    // the arrow keys are the second player's half of the keyboard in co-op
+    let arrows = if settings.players > 1 {
+        PlayerId::Two
+    } else {
+        PlayerId::One
+    };
+    let schemes = [
+        (
+            PlayerId::One,
+            [KeyCode::KeyA, KeyCode::KeyD, KeyCode::KeyW, KeyCode::KeyS],
+        ),
+        (
+            arrows,
+            [
+                KeyCode::ArrowLeft,
+                KeyCode::ArrowRight,
+                KeyCode::ArrowUp,
+                KeyCode::ArrowDown,
+            ],
+        ),
+    ];
+
+    for (player, keys) in schemes {
+        let keys = keys.into_iter().zip([
+            Direction::Left,
+            Direction::Right,
+            Direction::Up,
+            Direction::Down,
+        ]);
+        let just_pressed: Vec<Direction> = keys
+            .filter(|(k, _)| keyboard_input.just_pressed(*k))
+            .map(|(_, d)| d)
+            .collect();
+
+        for direction in move_intents(&just_pressed, rules.directions) {
+            debug!("sending {:?} intent for {:?}", direction, player);
+            intents.write(MoveIntent { player, direction });
+        }
     }
 }
 
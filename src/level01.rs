@@ -7,20 +7,26 @@ use LevelState::Level01;
 use LevelState::Level02;
 use bevy::prelude::*;
 
-use crate::animation::Animation;
-use crate::animation::AnimationType;
-use crate::controls::{Down, Left, Right, Up};
+use crate::controls::MoveIntent;
+use crate::in_game::ExitReached;
 use crate::in_game::LevelFinished;
 use crate::in_game::LevelStarted;
+use crate::lighting::LevelLighting;
+use crate::movement::MoveRules;
+use crate::objectives::{ExitLocked, LevelObjectives, Objective, ObjectiveKind};
+use crate::score::LevelPar;
 use crate::tutorial::CountDownFinished;
 use crate::tutorial::CountDownTutorialCounter;
 use crate::tutorial::Tutorial;
 use crate::tutorial::TutorialCountdown;
+use crate::tween::{Tween, TweenTrack};
 use crate::{
     app_states::{AppState, LevelState},
     controls::PlayerControlled,
+    grid::{GridIndex, GridRole},
     sprites::{ExfilSprite, MySprite, SpawnSprite, SpriteSheetTile},
     tiles::TileCoordinate,
+    transition::{StartTransition, TransitionEffect, no_transition},
 };
 
 // Constants
@@ -39,7 +45,7 @@ impl Plugin for Level01Plugin {
                     added_tutorial_components,
                     countdown_tutorial,
                     countdown_tutorial_finished,
-                    check_for_exit_level01,
+                    check_for_exit_level01.run_if(no_transition),
                 )
                     .run_if(in_state(Running))
                     .run_if(in_state(Level01)),
@@ -56,76 +62,87 @@ impl Plugin for Level01Plugin {
 
 // Systems
 fn start_level01(
+    mut commands: Commands,
     mut spawn_sprite: EventWriter<SpawnSprite>,
     mut started: EventWriter<LevelStarted>,
 ) {
     debug!("starting {}", NAME);
 
+    commands.insert_resource(MoveRules::default());
+    commands.insert_resource(LevelLighting::default());
+    commands.insert_resource(LevelPar {
+        moves: 4,
+        seconds: 10.0,
+    });
+    commands.insert_resource(LevelObjectives::new(vec![Objective::required(
+        ObjectiveKind::ReachExit,
+    )]));
+
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 0, y: 0, z: 0 },
+        coordinate: TileCoordinate::new(0, 0),
         tile: SpriteSheetTile::Player01,
         ..default()
     });
 
     /*
         spawn_sprite.write(SpawnSprite {
-            coordinate: TileCoordinate { x: -4, y: 0, z: 1 },
+            coordinate: TileCoordinate::new(-4, 0),
             tile: Tile::LeftDigiPadRound,
             tutorial: true,
             ..default()
         });
 
         spawn_sprite.write(SpawnSprite {
-            coordinate: TileCoordinate { x: 4, y: 0, z: 1 },
+            coordinate: TileCoordinate::new(4, 0),
             tile: Tile::RightDigiPadRound,
             tutorial: true,
             ..default()
         });
 
         spawn_sprite.write(SpawnSprite {
-            coordinate: TileCoordinate { x: -2, y: 2, z: 1 },
+            coordinate: TileCoordinate::new(-2, 2),
             tile: Tile::UpDigiPadRound,
             tutorial: true,
             ..default()
         });
 
         spawn_sprite.write(SpawnSprite {
-            coordinate: TileCoordinate { x: -2, y: -2, z: 1 },
+            coordinate: TileCoordinate::new(-2, -2),
             tile: Tile::DownDigiPadRound,
             tutorial: true,
             ..default()
         });
     */
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: -3, y: 0, z: 1 },
+        coordinate: TileCoordinate::new(-3, 0),
         tile: SpriteSheetTile::A,
         tutorial: true,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 3, y: 0, z: 1 },
+        coordinate: TileCoordinate::new(3, 0),
         tile: SpriteSheetTile::D,
         tutorial: true,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: -2, y: 1, z: 1 },
+        coordinate: TileCoordinate::new(-2, 1),
         tile: SpriteSheetTile::W,
         tutorial: true,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: -2, y: -1, z: 1 },
+        coordinate: TileCoordinate::new(-2, -1),
         tile: SpriteSheetTile::S,
         tutorial: true,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 2, y: 0, z: -1 },
+        coordinate: TileCoordinate::new(2, 0),
         tile: SpriteSheetTile::LevelExit01,
         ..default()
     });
@@ -139,42 +156,18 @@ fn added_tutorial_components(
 ) {
     for added in added_tutorials.iter() {
         commands.entity(added).insert(TutorialCountdown::new(4));
-        commands.entity(added).insert((
-            Animation::new(
-                Timer::new(Duration::from_millis(400), TimerMode::Repeating),
-                EaseFunction::SineInOut,
-            ),
-            AnimationType::Pulse,
-        ));
+        commands
+            .entity(added)
+            .insert(Tween::new(TweenTrack::pulse(Duration::from_millis(400))).repeating());
     }
 }
 
 fn countdown_tutorial(
-    mut right: EventReader<Right>,
-    mut left: EventReader<Left>,
-    mut up: EventReader<Up>,
-    mut down: EventReader<Down>,
+    mut intents: EventReader<MoveIntent>,
     countdowns: Query<Entity, With<TutorialCountdown>>,
     mut countdown_event: EventWriter<CountDownTutorialCounter>,
 ) {
-    let mut countdown = false;
-    for _ in right.read() {
-        countdown = true;
-    }
-
-    for _ in left.read() {
-        countdown = true;
-    }
-
-    for _ in up.read() {
-        countdown = true;
-    }
-
-    for _ in down.read() {
-        countdown = true;
-    }
-
-    if countdown {
+    if intents.read().count() > 0 {
         for c in countdowns.iter() {
             countdown_event.write(CountDownTutorialCounter(c));
         }
@@ -186,7 +179,7 @@ fn countdown_tutorial_finished(
     mut finished: EventReader<CountDownFinished>,
 ) {
     for f in finished.read() {
-        commands.entity(f.0).despawn();
+        commands.entity(f.0).try_despawn();
     }
 }
 
@@ -195,18 +188,21 @@ fn update_level01() {
 }
 
 fn check_for_exit_level01(
-    mut next_state: ResMut<NextState<LevelState>>,
+    mut transition: EventWriter<StartTransition>,
     players: Query<&TileCoordinate, (With<PlayerControlled>, Without<ExfilSprite>)>,
-    exfils: Query<&TileCoordinate, (With<ExfilSprite>, Without<PlayerControlled>)>,
+    exfils: Query<(), (With<ExfilSprite>, Without<ExitLocked>)>,
+    grid: Res<GridIndex>,
+    mut exit_reached: EventWriter<ExitReached>,
 ) {
     debug!("checking exit {}", NAME);
     for player_coordinate in players.iter() {
-        for exfil_coordinate in exfils.iter() {
-            if player_coordinate.eq2d(exfil_coordinate) {
-                // TODO: smoother transition, maybe with animation on an event
-                debug!("changing LevelState to {:?}", Level02);
-                next_state.set(Level02);
-            }
+        if grid
+            .with_role(player_coordinate.cell(), GridRole::Exit)
+            .any(|e| exfils.contains(e))
+        {
+            exit_reached.write(ExitReached(Level01));
+            debug!("changing LevelState to {:?}", Level02);
+            transition.write(StartTransition::level(Level02).with_effect(TransitionEffect::Iris));
         }
     }
 }
@@ -7,18 +7,25 @@ use LevelState::{Level02, Level03};
 use bevy::prelude::*;
 
 use crate::{
-    animation::{Animation, AnimationType},
     app_states::{AppState, LevelState},
     controls::PlayerControlled,
-    gold::PlayerPickedUpGoldCoins,
-    in_game::{LevelFinished, LevelStarted},
+    grid::{GridIndex, GridRole},
+    in_game::{ExitReached, LevelFinished, LevelStarted},
+    lighting::LevelLighting,
+    movement::MoveRules,
+    objectives::{ExitLocked, LevelObjectives, Objective, ObjectiveKind},
+    score::LevelPar,
+    scripting::ActiveLevelScript,
     sprites::{ExfilSprite, MySprite, SpawnSprite, SpriteSheetTile},
     tiles::TileCoordinate,
+    transition::{StartTransition, TransitionEffect, no_transition},
     tutorial::Tutorial,
+    tween::{Tween, TweenTrack},
 };
 
 // Constants
 const NAME: &str = "level02";
+const SCRIPT_FILE: &str = "levels/level02.script.ron";
 
 // Plugin
 pub struct Level02Plugin;
@@ -31,8 +38,7 @@ impl Plugin for Level02Plugin {
                 (
                     update_level02,
                     added_tutorial_components,
-                    check_for_tutorial_action,
-                    check_for_exit_level02,
+                    check_for_exit_level02.run_if(no_transition),
                 )
                     .run_if(in_state(Running))
                     .run_if(in_state(Level02)),
@@ -49,59 +55,74 @@ impl Plugin for Level02Plugin {
 
 // Systems
 fn start_level02(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
     mut spawn_sprite: EventWriter<SpawnSprite>,
     mut started: EventWriter<LevelStarted>,
 ) {
     debug!("starting {}", NAME);
 
+    commands.insert_resource(MoveRules::default());
+    commands.insert_resource(LevelLighting::default());
+    commands.insert_resource(LevelPar {
+        moves: 11,
+        seconds: 20.0,
+    });
+    commands.insert_resource(LevelObjectives::new(vec![
+        Objective::required(ObjectiveKind::CollectGold(31)),
+        Objective::required(ObjectiveKind::ReachExit),
+    ]));
+
+    commands.insert_resource(ActiveLevelScript::new(asset_server.load(SCRIPT_FILE)));
+
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 0, y: 0, z: 0 },
+        coordinate: TileCoordinate::new(0, 0),
         tile: SpriteSheetTile::Player01,
         color: Some(Color::linear_rgb(0.5, 0.5, 0.5)),
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 1, y: 0, z: 1 },
+        coordinate: TileCoordinate::new(1, 0),
         tile: SpriteSheetTile::LevelExit01,
         color: Some(Color::linear_rgb(0.0, 0.5, 0.5)),
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 2, y: 1, z: -1 },
+        coordinate: TileCoordinate::new(2, 1),
         tile: SpriteSheetTile::Grass,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 1, y: 1, z: -1 },
+        coordinate: TileCoordinate::new(1, 1),
         tile: SpriteSheetTile::GrassFlowers,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 0, y: 1, z: -1 },
+        coordinate: TileCoordinate::new(0, 1),
         tile: SpriteSheetTile::LongGrass,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 0, y: -2, z: -1 },
+        coordinate: TileCoordinate::new(0, -2),
         tile: SpriteSheetTile::GoldCoin,
         tutorial: true,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 2, y: -2, z: -1 },
+        coordinate: TileCoordinate::new(2, -2),
         tile: SpriteSheetTile::GoldCoins,
         tutorial: true,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 4, y: -2, z: -1 },
+        coordinate: TileCoordinate::new(4, -2),
         tile: SpriteSheetTile::GoldCoinBag,
         tutorial: true,
         ..default()
@@ -119,42 +140,27 @@ fn added_tutorial_components(
     added_tutorials: Query<Entity, Added<Tutorial>>,
 ) {
     for added in added_tutorials.iter() {
-        commands.entity(added).insert((
-            Animation::new(
-                Timer::new(Duration::from_millis(400), TimerMode::Repeating),
-                EaseFunction::SineInOut,
-            ),
-            AnimationType::Pulse,
-        ));
-    }
-}
-
-fn check_for_tutorial_action(
-    mut commands: Commands,
-    tutorial: Query<(Entity, &Animation, &AnimationType), With<Tutorial>>,
-    mut gold_pickup: EventReader<PlayerPickedUpGoldCoins>,
-) {
-    for _ in gold_pickup.read() {
-        for (t, _, _) in tutorial.iter() {
-            commands.entity(t).remove::<Tutorial>();
-            commands.entity(t).remove::<Animation>();
-            commands.entity(t).remove::<AnimationType>();
-        }
+        commands
+            .entity(added)
+            .insert(Tween::new(TweenTrack::pulse(Duration::from_millis(400))).repeating());
     }
 }
 
 fn check_for_exit_level02(
-    mut next_state: ResMut<NextState<LevelState>>,
+    mut transition: EventWriter<StartTransition>,
     players: Query<&TileCoordinate, (With<PlayerControlled>, Without<ExfilSprite>)>,
-    exfils: Query<&TileCoordinate, (With<ExfilSprite>, Without<PlayerControlled>)>,
+    exfils: Query<(), (With<ExfilSprite>, Without<ExitLocked>)>,
+    grid: Res<GridIndex>,
+    mut exit_reached: EventWriter<ExitReached>,
 ) {
     debug!("checking exit {}", NAME);
     for player_coordinate in players.iter() {
-        for exfil_coordinate in exfils.iter() {
-            if player_coordinate.eq2d(exfil_coordinate) {
-                // TODO: smoother transition, maybe with animation on an event
-                next_state.set(Level03);
-            }
+        if grid
+            .with_role(player_coordinate.cell(), GridRole::Exit)
+            .any(|e| exfils.contains(e))
+        {
+            exit_reached.write(ExitReached(Level02));
+            transition.write(StartTransition::level(Level03).with_effect(TransitionEffect::Slide));
         }
     }
 }
@@ -168,6 +174,7 @@ fn stop_level02(
     for sprite in sprites.iter() {
         commands.entity(sprite).despawn();
     }
+    commands.remove_resource::<ActiveLevelScript>();
     finished.write(LevelFinished);
 }
 
@@ -7,13 +7,19 @@ use LevelState::{Level03, Level04};
 use bevy::prelude::*;
 
 use crate::{
-    animation::{Animation, AnimationType},
     app_states::{AppState, LevelState},
-    controls::{Down, Left, PlayerControlled, Right, Up},
-    in_game::{LevelFinished, LevelStarted},
+    controls::{MoveIntent, PlayerControlled},
+    grid::{GridIndex, GridRole},
+    in_game::{ExitReached, LevelFinished, LevelStarted},
+    lighting::LevelLighting,
+    movement::MoveRules,
+    objectives::{ExitLocked, LevelObjectives, Objective, ObjectiveKind},
+    score::LevelPar,
     sprites::{ExfilSprite, MySprite, SpawnSprite, SpriteSheetTile},
     tiles::TileCoordinate,
+    transition::{StartTransition, TransitionEffect, no_transition},
     tutorial::{Tutorial, TutorialCountdown},
+    tween::{Tween, TweenTrack},
 };
 
 // Constants
@@ -31,7 +37,7 @@ impl Plugin for Level03Plugin {
                     update_level03,
                     added_tutorial_components,
                     countdown_tutorial,
-                    check_for_exit_level03,
+                    check_for_exit_level03.run_if(no_transition),
                 )
                     .run_if(in_state(Running))
                     .run_if(in_state(Level03)),
@@ -48,56 +54,68 @@ impl Plugin for Level03Plugin {
 
 // Systems
 fn start_level03(
+    mut commands: Commands,
     mut spawn_sprite: EventWriter<SpawnSprite>,
     mut started: EventWriter<LevelStarted>,
 ) {
     debug!("starting {}", NAME);
 
+    commands.insert_resource(MoveRules::default());
+    commands.insert_resource(LevelLighting::default());
+    commands.insert_resource(LevelPar {
+        moves: 7,
+        seconds: 15.0,
+    });
+    commands.insert_resource(LevelObjectives::new(vec![
+        Objective::optional(ObjectiveKind::FinishUnderMoves(7)),
+        Objective::required(ObjectiveKind::ReachExit),
+    ]));
+
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 0, y: 0, z: 0 },
+        coordinate: TileCoordinate::new(0, 0),
         tile: SpriteSheetTile::Player01,
         color: Some(Color::linear_rgb(0.5, 0.5, 0.5)),
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 1, y: 2, z: 3 },
+        coordinate: TileCoordinate::new(1, 2),
         tile: SpriteSheetTile::LevelExit01,
         color: Some(Color::linear_rgb(0.0, 0.5, 0.5)),
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 1, y: 1, z: 0 },
-        tile: SpriteSheetTile::BrickWall01,
+        coordinate: TileCoordinate::new(1, 1),
+        tile: SpriteSheetTile::Wall,
         tutorial: true,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 0, y: 1, z: 0 },
-        tile: SpriteSheetTile::BrickWall01,
+        coordinate: TileCoordinate::new(0, 1),
+        tile: SpriteSheetTile::Wall,
         tutorial: true,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 2, y: 1, z: 0 },
-        tile: SpriteSheetTile::BrickWall01,
+        coordinate: TileCoordinate::new(2, 1),
+        tile: SpriteSheetTile::Wall,
         tutorial: true,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 0, y: 2, z: 0 },
-        tile: SpriteSheetTile::BrickWall01,
+        coordinate: TileCoordinate::new(0, 2),
+        tile: SpriteSheetTile::Wall,
         tutorial: true,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 2, y: 2, z: 0 },
-        tile: SpriteSheetTile::BrickWall01,
+        coordinate: TileCoordinate::new(2, 2),
+        tile: SpriteSheetTile::Wall,
         tutorial: true,
         ..default()
     });
@@ -115,67 +133,42 @@ fn added_tutorial_components(
 ) {
     for added in added_tutorials.iter() {
         commands.entity(added).insert(TutorialCountdown::new(0));
-        commands.entity(added).insert((
-            Animation::new(
-                Timer::new(Duration::from_millis(400), TimerMode::Once),
-                EaseFunction::SineInOut,
-            ),
-            AnimationType::Pulse,
-        ));
+        commands
+            .entity(added)
+            .insert(Tween::new(TweenTrack::pulse(Duration::from_millis(400))));
     }
 }
 
 fn countdown_tutorial(
     mut commands: Commands,
-    mut right: EventReader<Right>,
-    mut left: EventReader<Left>,
-    mut up: EventReader<Up>,
-    mut down: EventReader<Down>,
+    mut intents: EventReader<MoveIntent>,
     countdowns: Query<Entity, With<Tutorial>>,
 ) {
-    let mut countdown = false;
-    for _ in right.read() {
-        countdown = true;
-    }
-
-    for _ in left.read() {
-        countdown = true;
-    }
-
-    for _ in up.read() {
-        countdown = true;
-    }
-
-    for _ in down.read() {
-        countdown = true;
-    }
-
-    if countdown {
+    if intents.read().count() > 0 {
         for c in countdowns.iter() {
             // pulse on every step
-            commands.entity(c).insert((
-                Animation::new(
-                    Timer::new(Duration::from_millis(100), TimerMode::Once),
-                    EaseFunction::SineInOut,
-                ),
-                AnimationType::Pulse,
-            ));
+            commands
+                .entity(c)
+                .insert(Tween::new(TweenTrack::pulse(Duration::from_millis(100))));
         }
     }
 }
 
 fn check_for_exit_level03(
-    mut next_state: ResMut<NextState<LevelState>>,
+    mut transition: EventWriter<StartTransition>,
     players: Query<&TileCoordinate, (With<PlayerControlled>, Without<ExfilSprite>)>,
-    exfils: Query<&TileCoordinate, (With<ExfilSprite>, Without<PlayerControlled>)>,
+    exfils: Query<(), (With<ExfilSprite>, Without<ExitLocked>)>,
+    grid: Res<GridIndex>,
+    mut exit_reached: EventWriter<ExitReached>,
 ) {
     debug!("checking exit {}", NAME);
     for player_coordinate in players.iter() {
-        for exfil_coordinate in exfils.iter() {
-            if player_coordinate.eq2d(exfil_coordinate) {
-                // TODO: smoother transition, maybe with animation on an event
-                next_state.set(Level04);
-            }
+        if grid
+            .with_role(player_coordinate.cell(), GridRole::Exit)
+            .any(|e| exfils.contains(e))
+        {
+            exit_reached.write(ExitReached(Level03));
+            transition.write(StartTransition::level(Level04).with_effect(TransitionEffect::Fade));
         }
     }
 }
@@ -7,18 +7,26 @@ use LevelState::Level04;
 use bevy::prelude::*;
 
 use crate::{
-    animation::{Animation, AnimationType},
     app_states::{AppState, LevelState},
-    controls::{Down, Left, PlayerControlled, Right, Up},
-    in_game::{LevelFinished, LevelStarted},
+    controls::{MoveIntent, PlayerControlled},
+    dialogue::{Dialogue, NpcTile},
+    grid::{GridIndex, GridRole},
+    in_game::{ExitReached, LevelFinished, LevelStarted},
     interaction::{Interacted, InteractionId, InteractionSource, InteractionTarget},
+    lighting::LevelLighting,
+    movement::MoveRules,
+    objectives::{ExitLocked, LevelObjectives, Objective, ObjectiveKind},
+    score::LevelPar,
     sprites::{ExfilSprite, MySprite, SpawnSprite, SpriteSheetTile},
-    tiles::{DoorTile, InteractableTile, TileCoordinate, TriggerTile},
+    tiles::{DoorOpened, DoorTile, InteractableTile, TileCoordinate, TriggerTile},
+    transition::{StartTransition, TransitionEffect, no_transition},
     tutorial::{CountDownFinished, CountDownTutorialCounter, Tutorial, TutorialCountdown},
+    tween::{Tween, TweenTrack},
 };
 
 // Constants
 const NAME: &str = "level04";
+const MECHANIC_DIALOGUE_FILE: &str = "dialogue/mechanic.dialogue.ron";
 
 // Plugin
 pub struct Level04Plugin;
@@ -36,8 +44,10 @@ impl Plugin for Level04Plugin {
                     update_level04,
                     added_tutorial_components,
                     added_interaction_components,
+                    added_npc_components,
+                    added_exit_components,
                     countdown_tutorial,
-                    check_for_exit_level04,
+                    check_for_exit_level04.run_if(no_transition),
                 )
                     .run_if(in_state(Running))
                     .run_if(in_state(Level04)),
@@ -68,32 +78,51 @@ impl Plugin for Level04Plugin {
 
 // Systems
 fn start_level04(
+    mut commands: Commands,
     mut spawn_sprite: EventWriter<SpawnSprite>,
     mut started: EventWriter<LevelStarted>,
 ) {
     debug!("starting {}", NAME);
 
+    commands.insert_resource(MoveRules::default());
+    commands.insert_resource(LevelLighting::default());
+    commands.insert_resource(LevelPar {
+        moves: 8,
+        seconds: 15.0,
+    });
+    commands.insert_resource(LevelObjectives::new(vec![
+        Objective::optional(ObjectiveKind::FlipAllLevers),
+        Objective::required(ObjectiveKind::ReachExit),
+    ]));
+
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 0, y: 0, z: 0 },
+        coordinate: TileCoordinate::new(0, 0),
         tile: SpriteSheetTile::Player01,
         color: Some(Color::linear_rgb(0.5, 0.5, 0.5)),
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 2, y: 1, z: 0 },
+        coordinate: TileCoordinate::new(2, 1),
         tile: SpriteSheetTile::MechanicDoor,
         color: Some(Color::linear_rgb(0.0, 0.5, 0.5)),
         tutorial: true,
+        ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: -2, y: 1, z: 0 },
+        coordinate: TileCoordinate::new(-2, 1),
         tile: SpriteSheetTile::BottomLeverLeft,
         tutorial: true,
         ..default()
     });
 
+    spawn_sprite.write(SpawnSprite {
+        coordinate: TileCoordinate::new(0, -2),
+        tile: SpriteSheetTile::Npc01,
+        ..default()
+    });
+
     started.write(LevelStarted);
 }
 
@@ -103,21 +132,31 @@ fn update_level04() {
 
 /// nothing fancy, we just have a lever and a door in level04
 fn interacted(
-    mut commands: Commands,
     mut interacted: EventReader<Interacted>,
-    interactables: Query<(Entity, &InteractableTile)>,
+    interactables: Query<(Entity, &TileCoordinate, &Sprite), With<InteractableTile>>,
     mut spawn_sprite: EventWriter<SpawnSprite>,
+    mut door_opened: EventWriter<DoorOpened>,
 ) {
     debug!("interacted {}", NAME);
+    // both players may use the lever in the same frame, the door is replaced once
+    let mut replaced = Vec::new();
     for i in interacted.read() {
         debug!("interacted {}: have message", NAME);
-        if let Ok((entity, _)) = interactables.get(i.0) {
+        if replaced.contains(&i.0) {
+            continue;
+        }
+        if let Ok((entity, coordinate, sprite)) = interactables.get(i.0) {
             debug!("interacted {}: found target", NAME);
-            commands.entity(entity).despawn();
+            replaced.push(entity);
+            door_opened.write(DoorOpened {
+                coordinate: coordinate.clone(),
+                sprite: sprite.clone(),
+            });
             spawn_sprite.write(SpawnSprite {
-                coordinate: TileCoordinate { x: 2, y: 1, z: 0 },
+                coordinate: coordinate.clone(),
                 tile: SpriteSheetTile::LevelExit01,
                 color: Some(Color::linear_rgb(0.0, 0.5, 0.5)),
+                replaces: Some(entity),
                 ..default()
             });
         }
@@ -142,58 +181,55 @@ fn added_interaction_components(
     }
 }
 
+fn added_npc_components(
+    mut commands: Commands,
+    added_npcs: Query<Entity, Added<NpcTile>>,
+    asset_server: Res<AssetServer>,
+) {
+    for npc in added_npcs.iter() {
+        debug!("added dialogue to npc {}", npc);
+        commands
+            .entity(npc)
+            .insert(Dialogue(asset_server.load(MECHANIC_DIALOGUE_FILE)));
+    }
+}
+
+/// the exit only appears once the door is open, make it noticeable
+fn added_exit_components(mut commands: Commands, added_exits: Query<Entity, Added<ExfilSprite>>) {
+    for exit in added_exits.iter() {
+        commands
+            .entity(exit)
+            .insert(Tween::new(TweenTrack::sequence(vec![
+                TweenTrack::popup(Duration::from_millis(300)),
+                TweenTrack::wiggle(Duration::from_millis(400)),
+            ])));
+    }
+}
+
 fn added_tutorial_components(
     mut commands: Commands,
     added_tutorials: Query<Entity, Added<Tutorial>>,
 ) {
     for added in added_tutorials.iter() {
         commands.entity(added).insert(TutorialCountdown::new(3));
-        commands.entity(added).insert((
-            Animation::new(
-                Timer::new(Duration::from_millis(400), TimerMode::Once),
-                EaseFunction::SineInOut,
-            ),
-            AnimationType::Pulse,
-        ));
+        commands
+            .entity(added)
+            .insert(Tween::new(TweenTrack::pulse(Duration::from_millis(400))));
     }
 }
 
 fn countdown_tutorial(
     mut commands: Commands,
-    mut right: EventReader<Right>,
-    mut left: EventReader<Left>,
-    mut up: EventReader<Up>,
-    mut down: EventReader<Down>,
+    mut intents: EventReader<MoveIntent>,
     countdowns: Query<Entity, With<Tutorial>>,
     mut tutorial_countdown: EventWriter<CountDownTutorialCounter>,
 ) {
-    let mut countdown = false;
-    for _ in right.read() {
-        countdown = true;
-    }
-
-    for _ in left.read() {
-        countdown = true;
-    }
-
-    for _ in up.read() {
-        countdown = true;
-    }
-
-    for _ in down.read() {
-        countdown = true;
-    }
-
-    if countdown {
+    if intents.read().count() > 0 {
         for c in countdowns.iter() {
             tutorial_countdown.write(CountDownTutorialCounter(c));
-            commands.entity(c).insert((
-                Animation::new(
-                    Timer::new(Duration::from_millis(100), TimerMode::Once),
-                    EaseFunction::SineInOut,
-                ),
-                AnimationType::Pulse,
-            ));
+            commands
+                .entity(c)
+                .insert(Tween::new(TweenTrack::pulse(Duration::from_millis(100))));
         }
     }
 }
@@ -205,23 +241,30 @@ fn countdown_tutorial_finished(
     debug!("countdown finished {}", NAME);
     for f in finished.read() {
         debug!("removing tutorial components for {}", f.0);
-        commands.entity(f.0).remove::<Tutorial>();
-        commands.entity(f.0).remove::<TutorialCountdown>();
+        // the tile may have been replaced while counting down
+        commands
+            .entity(f.0)
+            .try_remove::<(Tutorial, TutorialCountdown)>();
     }
 }
 
 fn check_for_exit_level04(
-    mut next_state: ResMut<NextState<LevelState>>,
+    mut transition: EventWriter<StartTransition>,
     players: Query<&TileCoordinate, (With<PlayerControlled>, Without<ExfilSprite>)>,
-    exfils: Query<&TileCoordinate, (With<ExfilSprite>, Without<PlayerControlled>)>,
+    exfils: Query<(), (With<ExfilSprite>, Without<ExitLocked>)>,
+    grid: Res<GridIndex>,
+    mut exit_reached: EventWriter<ExitReached>,
 ) {
     debug!("checking exit {}", NAME);
     for player_coordinate in players.iter() {
-        for exfil_coordinate in exfils.iter() {
-            if player_coordinate.eq2d(exfil_coordinate) {
-                // TODO: smoother transition, maybe with animation on an event
-                next_state.set(LevelState::Level05);
-            }
+        if grid
+            .with_role(player_coordinate.cell(), GridRole::Exit)
+            .any(|e| exfils.contains(e))
+        {
+            exit_reached.write(ExitReached(Level04));
+            transition.write(
+                StartTransition::level(LevelState::Level05).with_effect(TransitionEffect::Iris),
+            );
         }
     }
 }
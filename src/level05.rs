@@ -7,18 +7,27 @@ use LevelState::Level05;
 use bevy::prelude::*;
 
 use crate::{
-    animation::{Animation, AnimationType},
     app_states::{AppState, LevelState},
     controls::PlayerControlled,
-    health::{Health, Hearts, PickedUpEmptyHeart, PickedUpHearts},
-    in_game::{LevelFinished, LevelStarted},
+    grid::{GridIndex, GridRole},
+    health::{Health, Hearts},
+    in_game::{ExitReached, LevelFinished, LevelStarted},
+    lighting::LevelLighting,
+    movement::{CornerCutting, MoveDirections, MoveRules},
+    objectives::{ExitLocked, LevelObjectives, Objective, ObjectiveKind},
+    paths::TilePath,
+    score::LevelPar,
+    scripting::ActiveLevelScript,
     sprites::{ExfilSprite, MySprite, SpawnSprite, SpriteSheetTile},
-    tiles::{DoorTile, TileCoordinate},
+    tiles::{TileCoordinate, TileLayer},
+    transition::{StartTransition, no_transition},
     tutorial::Tutorial,
+    tween::{Tween, TweenTrack},
 };
 
 // Constants
 const NAME: &str = "level05";
+const SCRIPT_FILE: &str = "levels/level05.script.ron";
 
 // Plugin
 pub struct Level05Plugin;
@@ -31,23 +40,11 @@ impl Plugin for Level05Plugin {
                 (
                     added_player_controlled,
                     added_tutorial_components,
-                    check_for_exit_level05,
+                    check_for_exit_level05.run_if(no_transition),
                 )
                     .run_if(in_state(Running))
                     .run_if(in_state(Level05)),
             )
-            .add_systems(
-                Update,
-                (picked_up_heart)
-                    .run_if(on_event::<PickedUpHearts>)
-                    .run_if(in_state(Level05)),
-            )
-            .add_systems(
-                Update,
-                (picked_up_empty_heart)
-                    .run_if(on_event::<PickedUpEmptyHeart>)
-                    .run_if(in_state(Level05)),
-            )
             .add_systems(OnExit(Level05), stop_level05);
     }
 }
@@ -60,82 +57,119 @@ impl Plugin for Level05Plugin {
 
 // Systems
 fn start_level05(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
     mut started: EventWriter<LevelStarted>,
     mut spawn_sprite: EventWriter<SpawnSprite>,
 ) {
     debug!("starting {}", NAME);
 
+    commands.insert_resource(MoveRules {
+        directions: MoveDirections::Eight,
+        corners: CornerCutting::Never,
+    });
+    commands.insert_resource(LevelLighting { ambient: 0.2 });
+    commands.insert_resource(LevelPar {
+        moves: 9,
+        seconds: 20.0,
+    });
+    commands.insert_resource(LevelObjectives::new(vec![
+        Objective::required(ObjectiveKind::PickUpAllHearts),
+        Objective::required(ObjectiveKind::ReachExit),
+    ]));
+
+    commands.insert_resource(ActiveLevelScript::new(asset_server.load(SCRIPT_FILE)));
+
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 0, y: 0, z: 0 },
+        coordinate: TileCoordinate::new(0, 0),
         tile: SpriteSheetTile::Player01,
         color: Some(Color::linear_rgb(0.5, 0.5, 0.5)),
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 1, y: 1, z: 0 },
-        tile: SpriteSheetTile::BrickWall01,
+        coordinate: TileCoordinate::new(0, 2),
+        tile: SpriteSheetTile::Torch,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 3, y: 1, z: 0 },
-        tile: SpriteSheetTile::BrickWall01,
+        coordinate: TileCoordinate::new(1, 1),
+        tile: SpriteSheetTile::Wall,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 1, y: 2, z: 0 },
-        tile: SpriteSheetTile::BrickWall01,
+        coordinate: TileCoordinate::new(3, 1),
+        tile: SpriteSheetTile::Wall,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 2, y: 2, z: 0 },
-        tile: SpriteSheetTile::BrickWall01,
+        coordinate: TileCoordinate::new(1, 2),
+        tile: SpriteSheetTile::Wall,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 3, y: 2, z: 0 },
-        tile: SpriteSheetTile::BrickWall01,
+        coordinate: TileCoordinate::new(2, 2),
+        tile: SpriteSheetTile::Wall,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 1, y: 2, z: 1 },
+        coordinate: TileCoordinate::new(3, 2),
+        tile: SpriteSheetTile::Wall,
+        ..default()
+    });
+
+    spawn_sprite.write(SpawnSprite {
+        coordinate: TileCoordinate::new(1, 2),
         tile: SpriteSheetTile::FullHeart,
         color: Some(Color::linear_rgb(0.0, 1.0, 1.0)),
+        layer: Some(TileLayer::Overlay),
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 2, y: 2, z: 1 },
+        coordinate: TileCoordinate::new(2, 2),
         tile: SpriteSheetTile::FullHeart,
         color: Some(Color::linear_rgb(0.0, 1.0, 1.0)),
+        layer: Some(TileLayer::Overlay),
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 2, y: 1, z: 0 },
+        coordinate: TileCoordinate::new(2, 1),
         tile: SpriteSheetTile::MagicDoor,
         color: Some(Color::linear_rgb(0.0, 0.5, 0.5)),
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 2, y: -1, z: 0 },
+        coordinate: TileCoordinate::new(2, -1),
         tile: SpriteSheetTile::Heart,
         ..default()
     });
 
     spawn_sprite.write(SpawnSprite {
-        coordinate: TileCoordinate { x: 4, y: -1, z: 0 },
+        coordinate: TileCoordinate::new(4, -1),
         tile: SpriteSheetTile::EmptyHeart,
         tutorial: true,
         ..default()
     });
 
+    // guards the way to the empty heart
+    spawn_sprite.write(SpawnSprite {
+        coordinate: TileCoordinate::new(3, 0),
+        tile: SpriteSheetTile::Sawblade,
+        path: Some(TilePath {
+            waypoints: vec![(3, 0), (3, -2)],
+            beat: 0.5,
+        }),
+        ..default()
+    });
+
     started.write(LevelStarted);
 }
 
@@ -151,76 +185,32 @@ fn added_player_controlled(
     }
 }
 
-fn picked_up_heart(
-    mut commands: Commands,
-    mut pickups: EventReader<PickedUpHearts>,
-    doors: Query<Entity, With<DoorTile>>,
-    mut spawn_sprite: EventWriter<SpawnSprite>,
-) {
-    for _ in pickups.read() {
-        if let Ok(door) = doors.single() {
-            commands.entity(door).despawn();
-            spawn_sprite.write(SpawnSprite {
-                coordinate: TileCoordinate { x: 3, y: 2, z: 2 },
-                tile: SpriteSheetTile::FullHeart,
-                color: Some(Color::linear_rgb(0.0, 1.0, 1.0)),
-                ..default()
-            });
-            spawn_sprite.write(SpawnSprite {
-                coordinate: TileCoordinate { x: 2, y: 1, z: 0 },
-                tile: SpriteSheetTile::LevelExit01,
-                ..default()
-            });
-        }
-    }
-}
-
-fn picked_up_empty_heart(
-    mut commands: Commands,
-    mut pickups: EventReader<PickedUpEmptyHeart>,
-    hearts: Query<Entity, With<Hearts>>,
-    mut spawn_sprite: EventWriter<SpawnSprite>,
-) {
-    for _ in pickups.read() {
-        if let Ok(heart) = hearts.single() {
-            spawn_sprite.write(SpawnSprite {
-                coordinate: TileCoordinate { x: 3, y: 2, z: 1 },
-                tile: SpriteSheetTile::EmptyHeart,
-                color: Some(Color::linear_rgb(0.0, 0.5, 0.5)),
-                ..default()
-            });
-            commands.entity(heart).insert(Tutorial);
-        }
-    }
-}
-
 fn added_tutorial_components(
     mut commands: Commands,
     added_tutorials: Query<Entity, Added<Tutorial>>,
 ) {
     for added in added_tutorials.iter() {
-        commands.entity(added).insert((
-            Animation::new(
-                Timer::new(Duration::from_millis(400), TimerMode::Repeating),
-                EaseFunction::SineInOut,
-            ),
-            AnimationType::Pulse,
-        ));
+        commands
+            .entity(added)
+            .insert(Tween::new(TweenTrack::pulse(Duration::from_millis(400))).repeating());
     }
 }
 
 fn check_for_exit_level05(
-    mut next_state: ResMut<NextState<AppState>>,
+    mut transition: EventWriter<StartTransition>,
     players: Query<&TileCoordinate, (With<PlayerControlled>, Without<ExfilSprite>)>,
-    exfils: Query<&TileCoordinate, (With<ExfilSprite>, Without<PlayerControlled>)>,
+    exfils: Query<(), (With<ExfilSprite>, Without<ExitLocked>)>,
+    grid: Res<GridIndex>,
+    mut exit_reached: EventWriter<ExitReached>,
 ) {
     debug!("checking exit {}", NAME);
     for player_coordinate in players.iter() {
-        for exfil_coordinate in exfils.iter() {
-            if player_coordinate.eq2d(exfil_coordinate) {
-                // TODO: smoother transition, maybe with animation on an event
-                next_state.set(AppState::MainMenu);
-            }
+        if grid
+            .with_role(player_coordinate.cell(), GridRole::Exit)
+            .any(|e| exfils.contains(e))
+        {
+            exit_reached.write(ExitReached(Level05));
+            transition.write(StartTransition::state(AppState::MainMenu));
         }
     }
 }
@@ -234,6 +224,7 @@ fn stop_level05(
     for sprite in sprites.iter() {
         commands.entity(sprite).despawn();
     }
+    commands.remove_resource::<ActiveLevelScript>();
     finished.write(LevelFinished);
 }
 
@@ -0,0 +1,272 @@
+use bevy::{app::Plugin, platform::collections::HashMap};
+
+use AppState::Running;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    app_states::AppState,
+    controls::PlayerControlled,
+    grid::{GridIndex, GridRole},
+    sprites::SpriteSheetTile,
+    tiles::TileCoordinate,
+    tween::Tween,
+};
+
+// Constants
+const NAME: &str = "lighting";
+
+/// the lantern every player carries, only noticeable in dark levels
+const LANTERN: LightSource = LightSource {
+    radius: 3.5,
+    color: (1.0, 0.9, 0.7),
+    flicker: 0.05,
+};
+/// seconds between two flickers of the lights
+const FLICKER_TICK: f32 = 0.1;
+
+// Plugin
+pub struct LightingPlugin;
+
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // resources
+            .init_resource::<LevelLighting>()
+            .init_resource::<LightMap>()
+            .insert_resource(FlickerTick(Timer::from_seconds(
+                FLICKER_TICK,
+                TimerMode::Repeating,
+            )))
+            // observers
+            .add_observer(remember_tint)
+            // systems
+            .add_systems(
+                Update,
+                (equip_lanterns, update_light_map, apply_light)
+                    .chain()
+                    .run_if(in_state(Running)),
+            );
+    }
+}
+
+// Components
+/// shines on the tiles around it, solid tiles cast shadows
+#[derive(Component, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct LightSource {
+    /// tiles until the light is gone
+    pub radius: f32,
+    pub color: (f32, f32, f32),
+    /// how much of the light can flicker away
+    #[serde(default)]
+    pub flicker: f32,
+}
+
+/// sprite color before any light, from the atlas tint or the spawn
+#[derive(Component, Debug, Clone, Copy)]
+struct BaseTint(Color);
+
+// Resources
+/// darkness of the current level, levels insert this on start
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct LevelLighting {
+    /// light every tile gets without a light source, 1.0 turns lighting off
+    pub ambient: f32,
+}
+
+impl Default for LevelLighting {
+    fn default() -> Self {
+        LevelLighting { ambient: 1.0 }
+    }
+}
+
+/// light on every tile, cells no light reaches only get the ambient light
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub struct LightMap {
+    ambient: Vec3,
+    cells: HashMap<IVec2, Vec3>,
+}
+
+impl LightMap {
+    pub fn at(&self, cell: IVec2) -> Vec3 {
+        self.cells.get(&cell).copied().unwrap_or(self.ambient)
+    }
+}
+
+#[derive(Resource)]
+struct FlickerTick(Timer);
+
+// Events
+
+// Systems
+fn remember_tint(
+    trigger: Trigger<OnInsert, SpriteSheetTile>,
+    mut commands: Commands,
+    sprites: Query<&Sprite>,
+) {
+    if let Ok(sprite) = sprites.get(trigger.target()) {
+        commands
+            .entity(trigger.target())
+            .insert(BaseTint(sprite.color));
+    }
+}
+
+fn equip_lanterns(
+    mut commands: Commands,
+    added_players: Query<Entity, (Added<PlayerControlled>, Without<LightSource>)>,
+) {
+    for player in added_players.iter() {
+        commands.entity(player).insert(LANTERN);
+    }
+}
+
+/// recomputes the light when tiles changed, the level changed or the lights flicker
+fn update_light_map(
+    time: Res<Time>,
+    mut tick: ResMut<FlickerTick>,
+    lighting: Res<LevelLighting>,
+    grid: Res<GridIndex>,
+    lights: Query<(Entity, &LightSource, &TileCoordinate)>,
+    changed_lights: Query<(), Or<(Changed<LightSource>, Changed<TileCoordinate>)>>,
+    mut light_map: ResMut<LightMap>,
+) {
+    let flickered =
+        tick.0.tick(time.delta()).just_finished() && lights.iter().any(|(_, l, _)| l.flicker > 0.0);
+    if !(flickered || lighting.is_changed() || grid.is_changed() || !changed_lights.is_empty()) {
+        return;
+    }
+    debug!("updating {}", NAME);
+    let seconds = time.elapsed_secs();
+    let shining: Vec<(IVec2, LightSource, f32)> = lights
+        .iter()
+        .map(|(e, l, c)| (c.cell(), *l, flicker(seconds, e.index() as f32, l.flicker)))
+        .collect();
+    let computed = compute_light(&shining, lighting.ambient, |cell| {
+        grid.first(cell, GridRole::Solid).is_some()
+    });
+    light_map.set_if_neq(computed);
+}
+
+/// tweens own the sprite color while they run, the light catches up afterwards
+fn apply_light(
+    light_map: Res<LightMap>,
+    mut sprites: Query<(&TileCoordinate, &BaseTint, &mut Sprite), Without<Tween>>,
+) {
+    for (coordinate, tint, mut sprite) in sprites.iter_mut() {
+        let light = light_map.at(coordinate.cell());
+        let alpha = sprite.color.alpha();
+        let lit = if light == Vec3::ONE {
+            tint.0.with_alpha(alpha)
+        } else {
+            let base = tint.0.to_linear();
+            Color::LinearRgba(LinearRgba::new(
+                base.red * light.x,
+                base.green * light.y,
+                base.blue * light.z,
+                alpha,
+            ))
+        };
+        if sprite.color != lit {
+            sprite.color = lit;
+        }
+    }
+}
+
+// helper functions
+/// light of every cell the lights reach, added up on top of the ambient light.
+/// light fades out to the radius and stops at the first opaque cell, which is lit itself.
+pub fn compute_light(
+    lights: &[(IVec2, LightSource, f32)],
+    ambient: f32,
+    opaque: impl Fn(IVec2) -> bool,
+) -> LightMap {
+    let ambient = Vec3::splat(ambient.clamp(0.0, 1.0));
+    let mut cells: HashMap<IVec2, Vec3> = HashMap::default();
+    if ambient == Vec3::ONE {
+        return LightMap { ambient, cells };
+    }
+    for (origin, light, intensity) in lights {
+        let color = Vec3::new(light.color.0, light.color.1, light.color.2) * *intensity;
+        let reach = light.radius.ceil() as i32;
+        for x in -reach..=reach {
+            for y in -reach..=reach {
+                let offset = IVec2::new(x, y);
+                let falloff = 1.0 - offset.as_vec2().length() / light.radius;
+                if falloff <= 0.0 || !in_sight(*origin, *origin + offset, &opaque) {
+                    continue;
+                }
+                let cell = cells.entry(*origin + offset).or_insert(ambient);
+                *cell = (*cell + color * falloff).min(Vec3::ONE);
+            }
+        }
+    }
+    LightMap { ambient, cells }
+}
+
+/// no opaque cell between the two cells, the cells themselves don't count
+fn in_sight(from: IVec2, to: IVec2, opaque: &impl Fn(IVec2) -> bool) -> bool {
+    let steps = (to - from).abs().max_element();
+    (1..steps).all(|i| {
+        let at = from
+            .as_vec2()
+            .lerp(to.as_vec2(), i as f32 / steps as f32)
+            .round()
+            .as_ivec2();
+        !opaque(at)
+    })
+}
+
+/// intensity between 1.0 - amount and 1.0, every light flickers on its own
+fn flicker(seconds: f32, seed: f32, amount: f32) -> f32 {
+    let wave = (seconds * 7.0 + seed).sin() * (seconds * 13.0 + seed * 1.7).sin();
+    1.0 - amount * (0.5 + 0.5 * wave)
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    const TORCH: LightSource = LightSource {
+        radius: 4.0,
+        color: (1.0, 0.5, 0.0),
+        flicker: 0.0,
+    };
+
+    #[test]
+    fn should_cast_shadows_behind_solid_tiles() {
+        // given
+        let wall = IVec2::new(2, 0);
+
+        // when
+        let light = compute_light(&[(IVec2::ZERO, TORCH, 1.0)], 0.1, |cell| cell == wall);
+
+        // then
+        assert!(light.at(wall).x > 0.1);
+        assert_eq!(light.at(IVec2::new(3, 0)), Vec3::splat(0.1));
+        assert!(light.at(IVec2::new(0, 3)).x > 0.1);
+        assert_eq!(light.at(IVec2::new(9, 9)), Vec3::splat(0.1));
+    }
+
+    #[test]
+    fn should_add_up_colored_lights_up_to_full_light() {
+        // given
+        let blue = LightSource {
+            color: (0.0, 0.0, 1.0),
+            ..TORCH
+        };
+        let lights = [
+            (IVec2::ZERO, TORCH, 1.0),
+            (IVec2::ZERO, TORCH, 1.0),
+            (IVec2::new(1, 0), blue, 1.0),
+        ];
+
+        // when
+        let light = compute_light(&lights, 0.0, |_| false);
+
+        // then
+        assert_eq!(light.at(IVec2::ZERO), Vec3::new(1.0, 1.0, 0.75));
+        assert!(light.at(IVec2::new(-2, 0)).z < light.at(IVec2::new(2, 0)).z);
+    }
+}
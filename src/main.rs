@@ -11,23 +11,47 @@ use splash::SplashPlugin;
 use sprites::SpritesPlugin;
 use tiles::TilesPlugin;
 
-use crate::animation::AnimationPlugin;
+use crate::atlas::AtlasPlugin;
+use crate::autotile::AutotilePlugin;
+use crate::dialogue::DialoguePlugin;
+use crate::effects::EffectsPlugin;
+use crate::game_audio::GameAudioPlugin;
 use crate::game_camera::GameCameraPlugin;
+use crate::gamepad_controller::GamepadControllerPlugin;
 use crate::gold::GoldPlugin;
+use crate::grid::GridPlugin;
 use crate::health::HealthPlugin;
 use crate::in_game_ui::InGameUIPlugin;
 use crate::interaction::InteractionPlugin;
 use crate::level03::Level03Plugin;
 use crate::level04::Level04Plugin;
 use crate::level05::Level05Plugin;
+use crate::lighting::LightingPlugin;
+use crate::minimap::MinimapPlugin;
 use crate::movement::MovementPlugin;
+use crate::objectives::ObjectivesPlugin;
+use crate::paths::PathsPlugin;
+use crate::pause::PausePlugin;
+use crate::progress::ProgressPlugin;
+use crate::score::ScorePlugin;
+use crate::scripting::ScriptingPlugin;
+use crate::settings::SettingsPlugin;
+use crate::teleport::TeleportPlugin;
+use crate::transition::TransitionPlugin;
 use crate::tutorial::TutorialPlugin;
+use crate::tween::TweenPlugin;
 
-mod animation;
 mod app_states;
+mod atlas;
+mod autotile;
 mod controls;
+mod dialogue;
+mod effects;
+mod game_audio;
 mod game_camera;
+mod gamepad_controller;
 mod gold;
+mod grid;
 mod health;
 mod in_game;
 mod in_game_ui;
@@ -38,12 +62,25 @@ mod level02;
 mod level03;
 mod level04;
 mod level05;
+mod lighting;
 mod main_menu;
+mod minimap;
 mod movement;
+mod objectives;
+mod paths;
+mod pause;
+mod progress;
+mod score;
+mod scripting;
+mod settings;
 mod splash;
 mod sprites;
+mod storage;
+mod teleport;
 mod tiles;
+mod transition;
 mod tutorial;
+mod tween;
 
 fn main() {
     App::new()
@@ -59,7 +96,7 @@ fn main() {
                 }),
         )
         .add_plugins((
-            AnimationPlugin,
+            TweenPlugin,
             AppStatesPlugin,
             MainMenuPlugin,
             SplashPlugin,
@@ -75,7 +112,29 @@ fn main() {
             InteractionPlugin,
             HealthPlugin,
         ))
-        .add_plugins(InGameUIPlugin)
+        .add_plugins((
+            InGameUIPlugin,
+            ScriptingPlugin,
+            DialoguePlugin,
+            ObjectivesPlugin,
+            ScorePlugin,
+            ProgressPlugin,
+            SettingsPlugin,
+            PausePlugin,
+            GameAudioPlugin,
+            EffectsPlugin,
+            TransitionPlugin,
+            GamepadControllerPlugin,
+            AtlasPlugin,
+        ))
+        .add_plugins((
+            AutotilePlugin,
+            GridPlugin,
+            TeleportPlugin,
+            PathsPlugin,
+            LightingPlugin,
+            MinimapPlugin,
+        ))
         .add_plugins((
             Level01Plugin,
             Level02Plugin,
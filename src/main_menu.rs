@@ -1,7 +1,13 @@
 use AppState::MainMenu;
 use bevy::prelude::*;
 
-use crate::{app_states::AppState, gold::FinalPlayerGoldAmount};
+use crate::{
+    app_states::{AppState, LevelState, MenuState},
+    gold::FinalPlayerGoldAmount,
+    progress::LevelProgress,
+    score::BestResults,
+    transition::StartTransition,
+};
 
 // Constants
 const NAME: &str = "main_menu";
@@ -9,24 +15,32 @@ const NAME: &str = "main_menu";
 const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
 const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
 const PRESSED_BUTTON: Color = Color::srgb(0.35, 0.75, 0.35);
+const LOCKED_TEXT: Color = Color::srgb(0.5, 0.5, 0.5);
 
 // Plugin
 pub struct MainMenuPlugin;
 
 impl Plugin for MainMenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(MainMenu), start_main_menu)
+        app.add_systems(OnEnter(MenuState::Main), start_main_menu)
             .add_systems(
                 Update,
                 (update_main_menu, report_last_run).run_if(in_state(MainMenu)),
             )
-            .add_systems(OnExit(MainMenu), stop_main_menu);
+            .add_systems(OnExit(MenuState::Main), stop_main_menu)
+            .add_systems(OnEnter(MenuState::LevelSelect), start_level_select)
+            .add_systems(OnExit(MenuState::LevelSelect), stop_level_select);
     }
 }
 
 // Components
 #[derive(Component, Debug)]
-struct ButtonTargetState(AppState);
+enum ButtonTarget {
+    State(AppState),
+    View(MenuState),
+    /// starts the run directly in this level
+    Level(LevelState),
+}
 
 // Resources
 #[derive(Resource)]
@@ -34,6 +48,11 @@ struct MainMenuData {
     main_menu_layout: Entity,
 }
 
+#[derive(Resource)]
+struct LevelSelectData {
+    level_select_layout: Entity,
+}
+
 // Events
 
 // Systems
@@ -72,7 +91,7 @@ fn start_main_menu(mut commands: Commands) {
                     display: Display::Grid,
                     justify_items: JustifyItems::Center,
                     padding: UiRect::all(Val::Px(12.0)),
-                    grid_template_columns: RepeatedGridTrack::flex(2, 1.0),
+                    grid_template_columns: RepeatedGridTrack::flex(4, 1.0),
                     ..default()
                 })
                 .insert(Name::new("Main"))
@@ -82,14 +101,28 @@ fn start_main_menu(mut commands: Commands) {
                         builder,
                         start_name.clone(),
                         start_name.as_str(),
-                        ButtonTargetState(AppState::Running),
+                        ButtonTarget::Level(LevelState::Level01),
+                    );
+                    let levels_name = Name::new("Levels");
+                    spawn_button_bundle(
+                        builder,
+                        levels_name.clone(),
+                        levels_name.as_str(),
+                        ButtonTarget::View(MenuState::LevelSelect),
+                    );
+                    let settings_name = Name::new("Settings");
+                    spawn_button_bundle(
+                        builder,
+                        settings_name.clone(),
+                        settings_name.as_str(),
+                        ButtonTarget::View(MenuState::Settings),
                     );
                     let quit_name = Name::new("GGs");
                     spawn_button_bundle(
                         builder,
                         quit_name.clone(),
                         quit_name.as_str(),
-                        ButtonTargetState(AppState::Quitting),
+                        ButtonTarget::State(AppState::Quitting),
                     );
                 });
         })
@@ -99,6 +132,105 @@ fn start_main_menu(mut commands: Commands) {
     commands.insert_resource(MainMenuData { main_menu_layout });
 }
 
+fn start_level_select(
+    mut commands: Commands,
+    progress: Res<LevelProgress>,
+    best_results: Res<BestResults>,
+) {
+    debug!("starting level select {}", NAME);
+
+    let level_select_layout = commands
+        .spawn(Node {
+            display: Display::Grid,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            grid_template_columns: vec![GridTrack::auto()],
+            grid_template_rows: vec![GridTrack::auto(), GridTrack::flex(1.0), GridTrack::auto()],
+            ..default()
+        })
+        .insert(Name::new("Level Select Layout"))
+        .with_children(|builder| {
+            // Header
+            builder
+                .spawn(Node {
+                    display: Display::Grid,
+                    justify_items: JustifyItems::Center,
+                    padding: UiRect::all(Val::Px(12.0)),
+                    ..default()
+                })
+                .insert(Name::new("Header"))
+                .with_children(|builder| {
+                    spawn_nested_text_bundle(builder, 40.0, "Levels");
+                });
+            // Levels
+            builder
+                .spawn(Node {
+                    display: Display::Grid,
+                    align_content: AlignContent::Start,
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(12.0)),
+                    row_gap: Val::Px(8.0),
+                    column_gap: Val::Px(24.0),
+                    grid_template_columns: vec![
+                        GridTrack::flex(1.0),
+                        GridTrack::auto(),
+                        GridTrack::auto(),
+                        GridTrack::flex(1.0),
+                    ],
+                    ..default()
+                })
+                .insert(Name::new("Levels"))
+                .with_children(|builder| {
+                    for (i, level) in LevelState::ALL.iter().enumerate() {
+                        let unlocked = progress.is_unlocked(level);
+                        let status = match (unlocked, best_results.0.get(level)) {
+                            (false, _) => "locked".to_string(),
+                            (true, None) => "not finished".to_string(),
+                            (true, Some(best)) => format!(
+                                "{}   {} moves, {:.1}s",
+                                best.stars_text(),
+                                best.moves,
+                                best.seconds
+                            ),
+                        };
+                        let color = if unlocked {
+                            Color::srgb(0.9, 0.9, 0.9)
+                        } else {
+                            LOCKED_TEXT
+                        };
+                        // empty first column centers the level list
+                        builder.spawn(Node::default());
+                        builder
+                            .spawn(Text::new(format!("{}. {}", i + 1, level.name())))
+                            .insert(TextColor(color));
+                        builder.spawn(Text::new(status)).insert(TextColor(color));
+                        if unlocked {
+                            spawn_small_button_bundle(builder, "Play", ButtonTarget::Level(*level));
+                        } else {
+                            builder.spawn(Node::default());
+                        }
+                    }
+                });
+            // Footer
+            builder
+                .spawn(Node {
+                    display: Display::Grid,
+                    justify_items: JustifyItems::Center,
+                    padding: UiRect::all(Val::Px(12.0)),
+                    ..default()
+                })
+                .insert(Name::new("Footer"))
+                .with_children(|builder| {
+                    spawn_small_button_bundle(builder, "Back", ButtonTarget::View(MenuState::Main));
+                });
+        })
+        .id();
+
+    commands.insert_resource(LevelSelectData {
+        level_select_layout,
+    });
+}
+
 fn report_last_run(mut final_gold: EventReader<FinalPlayerGoldAmount>) {
     for final_player_gold in final_gold.read() {
         debug!(
@@ -110,18 +242,26 @@ fn report_last_run(mut final_gold: EventReader<FinalPlayerGoldAmount>) {
 
 fn update_main_menu(
     mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor, &ButtonTargetState),
+        (&Interaction, &mut BackgroundColor, &ButtonTarget),
         (Changed<Interaction>, With<Button>),
     >,
     mut next_state: ResMut<NextState<AppState>>,
+    mut next_view: ResMut<NextState<MenuState>>,
+    mut transition: EventWriter<StartTransition>,
 ) {
     debug!("updating {}", NAME);
-    for (interaction, mut color, target_state) in &mut interaction_query {
+    for (interaction, mut color, target) in &mut interaction_query {
         match *interaction {
             Interaction::Pressed => {
-                debug!("button pressed");
+                debug!("button pressed: {:?}", target);
                 *color = PRESSED_BUTTON.into();
-                next_state.set(target_state.0.clone());
+                match target {
+                    ButtonTarget::State(state) => next_state.set(state.clone()),
+                    ButtonTarget::View(view) => next_view.set(*view),
+                    ButtonTarget::Level(level) => {
+                        transition.write(StartTransition::level(*level));
+                    }
+                }
             }
             Interaction::Hovered => {
                 debug!("button hovered");
@@ -140,6 +280,13 @@ fn stop_main_menu(mut commands: Commands, menu_data: Res<MainMenuData>) {
     commands.entity(menu_data.main_menu_layout).despawn();
 }
 
+fn stop_level_select(mut commands: Commands, level_select_data: Res<LevelSelectData>) {
+    debug!("stopping level select {}", NAME);
+    commands
+        .entity(level_select_data.level_select_layout)
+        .despawn();
+}
+
 // helper functions
 fn spawn_nested_text_bundle(child_commands: &mut ChildSpawnerCommands, font_size: f32, text: &str) {
     child_commands
@@ -155,7 +302,7 @@ fn spawn_button_bundle(
     child_commands: &mut ChildSpawnerCommands,
     button_name_component: Name,
     button_text: &str,
-    button_target_state: ButtonTargetState,
+    button_target: ButtonTarget,
 ) {
     child_commands
         .spawn(Node {
@@ -186,10 +333,37 @@ fn spawn_button_bundle(
                         })
                         .insert(TextColor(Color::srgb(0.9, 0.9, 0.9)));
                 })
-                .insert(button_target_state);
+                .insert(button_target);
         });
 }
 
+fn spawn_small_button_bundle(
+    child_commands: &mut ChildSpawnerCommands,
+    button_text: &str,
+    button_target: ButtonTarget,
+) {
+    child_commands
+        .spawn(Button)
+        .insert(Node {
+            padding: UiRect::axes(Val::Px(16.), Val::Px(6.)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        })
+        .insert(BackgroundColor(NORMAL_BUTTON))
+        .insert(Name::new(button_text.to_string()))
+        .with_children(|parent| {
+            parent
+                .spawn(Text::new(button_text))
+                .insert(TextFont {
+                    font_size: 24.0,
+                    ..default()
+                })
+                .insert(TextColor(Color::srgb(0.9, 0.9, 0.9)));
+        })
+        .insert(button_target);
+}
+
 // tests
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,419 @@
+use bevy::{
+    app::Plugin,
+    platform::collections::{HashMap, HashSet},
+};
+
+use AppState::Running;
+use bevy::prelude::*;
+
+use crate::{
+    app_states::AppState,
+    controls::{PlayerControlled, controls_unlocked},
+    gold::Gold,
+    grid::GridIndex,
+    health::{EmptyHeart, Hearts},
+    in_game::LevelStarted,
+    paths::Hazard,
+    settings::Settings,
+    sprites::ExfilSprite,
+    tiles::{DoorTile, FloorTile, SolidTile, TileCoordinate, TileLayer},
+};
+
+// Constants
+const NAME: &str = "minimap";
+
+const TOGGLE_KEY: KeyCode = KeyCode::KeyM;
+/// pixels per tile in the corner
+const CORNER_CELL: f32 = 4.0;
+/// pixels per tile on the full screen map
+const FULL_SCREEN_CELL: f32 = 12.0;
+/// tiles around the players they explore
+const REVEAL_RADIUS: i32 = 3;
+
+const PANEL_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+const FULL_SCREEN_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.85);
+const FLOOR_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+const WALL_COLOR: Color = Color::srgb(0.7, 0.7, 0.7);
+const DOOR_COLOR: Color = Color::srgb(0.6, 0.4, 0.2);
+const PICKUP_COLOR: Color = Color::srgb(0.9, 0.8, 0.1);
+const EXIT_COLOR: Color = Color::srgb(0.2, 0.8, 0.3);
+const HAZARD_COLOR: Color = Color::srgb(0.9, 0.2, 0.2);
+const PLAYER_COLOR: Color = Color::srgb(0.3, 0.6, 1.0);
+
+// Plugin
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // resources
+            .init_resource::<MinimapView>()
+            .init_resource::<Explored>()
+            // systems
+            .add_systems(OnEnter(Running), start_minimap)
+            .add_systems(
+                Update,
+                (
+                    toggle_minimap.run_if(controls_unlocked),
+                    forget_explored,
+                    explore,
+                    draw_minimap,
+                )
+                    .chain()
+                    .run_if(in_state(Running)),
+            )
+            .add_systems(OnExit(Running), stop_minimap);
+    }
+}
+
+// Components
+#[derive(Component)]
+struct MinimapUI;
+
+/// holds one node per cell, spawned once per level. cells without a mark hide their node.
+#[derive(Component, Default)]
+struct MinimapCells(HashMap<IVec2, Entity>);
+
+#[derive(Component)]
+struct MinimapCell;
+
+/// what a tile is shown as, higher marks cover lower ones on the same cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MapMark {
+    Floor,
+    Wall,
+    Door,
+    Pickup,
+    Exit,
+    Hazard,
+    Player,
+}
+
+impl MapMark {
+    fn color(&self) -> Color {
+        match self {
+            MapMark::Floor => FLOOR_COLOR,
+            MapMark::Wall => WALL_COLOR,
+            MapMark::Door => DOOR_COLOR,
+            MapMark::Pickup => PICKUP_COLOR,
+            MapMark::Exit => EXIT_COLOR,
+            MapMark::Hazard => HAZARD_COLOR,
+            MapMark::Player => PLAYER_COLOR,
+        }
+    }
+}
+
+// Resources
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum MinimapView {
+    #[default]
+    Corner,
+    FullScreen,
+}
+
+/// cells the players have seen in the current level
+#[derive(Resource, Debug, Default)]
+struct Explored(HashSet<IVec2>);
+
+// Events
+
+// Systems
+fn start_minimap(mut commands: Commands, view: Res<MinimapView>) {
+    debug!("starting {}", NAME);
+    commands.spawn((
+        MinimapUI,
+        panel_node(*view),
+        BackgroundColor(panel_color(*view)),
+        children![(MinimapCells::default(), Node::default())],
+    ));
+}
+
+fn toggle_minimap(keyboard_input: Res<ButtonInput<KeyCode>>, mut view: ResMut<MinimapView>) {
+    if keyboard_input.just_pressed(TOGGLE_KEY) {
+        *view = match *view {
+            MinimapView::Corner => MinimapView::FullScreen,
+            MinimapView::FullScreen => MinimapView::Corner,
+        };
+        debug!("switching {} to {:?}", NAME, *view);
+    }
+}
+
+fn forget_explored(mut started: EventReader<LevelStarted>, mut explored: ResMut<Explored>) {
+    if started.read().count() > 0 {
+        explored.0.clear();
+    }
+}
+
+fn explore(
+    players: Query<&TileCoordinate, (With<PlayerControlled>, Changed<TileCoordinate>)>,
+    mut explored: ResMut<Explored>,
+) {
+    let r = REVEAL_RADIUS;
+    for coordinate in players.iter() {
+        for x in -r..=r {
+            for y in -r..=r {
+                if x * x + y * y <= r * r {
+                    explored.0.insert(coordinate.cell() + IVec2::new(x, y));
+                }
+            }
+        }
+    }
+}
+
+/// recolors the map whenever something on the grid changed, so opened doors and picked up
+/// pickups vanish right away. tiles without a mark, like tutorial letters, aren't shown.
+fn draw_minimap(
+    mut commands: Commands,
+    view: Res<MinimapView>,
+    settings: Res<Settings>,
+    explored: Res<Explored>,
+    grid: Res<GridIndex>,
+    mut started: EventReader<LevelStarted>,
+    tiles: Query<(
+        &TileCoordinate,
+        Has<PlayerControlled>,
+        Has<Hazard>,
+        Has<ExfilSprite>,
+        (Has<Gold>, Has<Hearts>, Has<EmptyHeart>),
+        Has<DoorTile>,
+        Has<SolidTile>,
+        Has<FloorTile>,
+    )>,
+    mut panels: Query<(&mut Node, &mut BackgroundColor), (With<MinimapUI>, Without<MinimapCells>)>,
+    mut maps: Query<(Entity, &mut MinimapCells, &mut Node), Without<MinimapUI>>,
+    mut cells: Query<
+        (&mut Node, &mut BackgroundColor, &mut Visibility),
+        (With<MinimapCell>, Without<MinimapUI>, Without<MinimapCells>),
+    >,
+) {
+    let level_started = started.read().count() > 0;
+    if !(level_started
+        || view.is_changed()
+        || grid.is_changed()
+        || explored.is_changed()
+        || settings.is_changed())
+    {
+        return;
+    }
+    let (Ok((mut panel, mut background)), Ok((map, mut nodes, mut map_node))) =
+        (panels.single_mut(), maps.single_mut())
+    else {
+        return;
+    };
+    debug!("updating {}", NAME);
+    if level_started {
+        for (_, node) in nodes.0.drain() {
+            commands.entity(node).despawn();
+        }
+    }
+
+    let marks = minimap_marks(
+        tiles
+            .iter()
+            // overlays only decorate the tiles below
+            .filter(|(c, ..)| c.z != TileLayer::Overlay.z())
+            .filter_map(|(c, player, hazard, exit, pickups, door, solid, floor)| {
+                let pickup = pickups.0 || pickups.1 || pickups.2;
+                let mark = match (player, hazard, exit, pickup, door, solid, floor) {
+                    (true, ..) => MapMark::Player,
+                    (_, true, ..) => MapMark::Hazard,
+                    (_, _, true, ..) => MapMark::Exit,
+                    (_, _, _, true, ..) => MapMark::Pickup,
+                    (_, _, _, _, true, ..) => MapMark::Door,
+                    (_, _, _, _, _, true, _) => MapMark::Wall,
+                    (_, _, _, _, _, _, true) => MapMark::Floor,
+                    _ => return None,
+                };
+                Some((c.cell(), mark))
+            }),
+        settings.minimap_explored_only.then_some(&explored.0),
+    );
+    let min = marks.keys().copied().reduce(IVec2::min).unwrap_or_default();
+    let max = marks.keys().copied().reduce(IVec2::max).unwrap_or_default();
+    let size = if marks.is_empty() {
+        IVec2::ZERO
+    } else {
+        max - min + IVec2::ONE
+    };
+    let cell_px = match *view {
+        MinimapView::Corner => CORNER_CELL,
+        MinimapView::FullScreen => FULL_SCREEN_CELL,
+    };
+
+    panel.set_if_neq(panel_node(*view));
+    background.set_if_neq(BackgroundColor(panel_color(*view)));
+    map_node.width = Val::Px(size.x as f32 * cell_px);
+    map_node.height = Val::Px(size.y as f32 * cell_px);
+
+    // only the nodes whose cell moved, changed its mark or appeared are touched
+    let cell_node = |cell: IVec2| Node {
+        position_type: PositionType::Absolute,
+        left: Val::Px((cell.x - min.x) as f32 * cell_px),
+        // ui grows down, tiles grow up
+        top: Val::Px((max.y - cell.y) as f32 * cell_px),
+        width: Val::Px(cell_px),
+        height: Val::Px(cell_px),
+        ..default()
+    };
+    for (cell, node) in nodes.0.iter() {
+        let Ok((mut cell_layout, mut color, mut visibility)) = cells.get_mut(*node) else {
+            continue;
+        };
+        match marks.get(cell) {
+            Some(mark) => {
+                cell_layout.set_if_neq(cell_node(*cell));
+                color.set_if_neq(BackgroundColor(mark.color()));
+                visibility.set_if_neq(Visibility::Inherited);
+            }
+            None => {
+                visibility.set_if_neq(Visibility::Hidden);
+            }
+        }
+    }
+    for (cell, mark) in marks {
+        if nodes.0.contains_key(&cell) {
+            continue;
+        }
+        let node = commands
+            .spawn((
+                MinimapCell,
+                cell_node(cell),
+                BackgroundColor(mark.color()),
+                Visibility::Inherited,
+                ChildOf(map),
+            ))
+            .id();
+        nodes.0.insert(cell, node);
+    }
+}
+
+fn stop_minimap(mut commands: Commands, ui: Query<Entity, With<MinimapUI>>) {
+    debug!("stopping {}", NAME);
+    for x in ui.iter() {
+        commands.entity(x).despawn();
+    }
+}
+
+// helper functions
+/// the top mark of every cell, only explored cells if given
+fn minimap_marks(
+    tiles: impl Iterator<Item = (IVec2, MapMark)>,
+    explored: Option<&HashSet<IVec2>>,
+) -> HashMap<IVec2, MapMark> {
+    let mut marks: HashMap<IVec2, MapMark> = HashMap::default();
+    for (cell, mark) in tiles {
+        if explored.is_some_and(|e| !e.contains(&cell)) {
+            continue;
+        }
+        let top = marks.entry(cell).or_insert(mark);
+        *top = (*top).max(mark);
+    }
+    marks
+}
+
+fn panel_node(view: MinimapView) -> Node {
+    match view {
+        MinimapView::Corner => Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(12.0),
+            bottom: Val::Px(12.0),
+            padding: UiRect::all(Val::Px(4.0)),
+            ..default()
+        },
+        MinimapView::FullScreen => Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(0.0),
+            top: Val::Px(0.0),
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+    }
+}
+
+fn panel_color(view: MinimapView) -> Color {
+    match view {
+        MinimapView::Corner => PANEL_COLOR,
+        MinimapView::FullScreen => FULL_SCREEN_COLOR,
+    }
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use crate::grid::GridPlugin;
+
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn should_show_the_top_mark_of_explored_cells() {
+        // given
+        let tiles = [
+            (IVec2::new(0, 0), MapMark::Floor),
+            (IVec2::new(0, 0), MapMark::Player),
+            (IVec2::new(1, 0), MapMark::Door),
+            (IVec2::new(1, 0), MapMark::Floor),
+            (IVec2::new(5, 0), MapMark::Exit),
+        ];
+        let explored: HashSet<IVec2> = [IVec2::new(0, 0), IVec2::new(1, 0)].into_iter().collect();
+
+        // when
+        let all = minimap_marks(tiles.into_iter(), None);
+        let seen = minimap_marks(tiles.into_iter(), Some(&explored));
+
+        // then
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[&IVec2::new(0, 0)], MapMark::Player);
+        assert_eq!(all[&IVec2::new(1, 0)], MapMark::Door);
+        assert_eq!(seen.len(), 2);
+        assert!(!seen.contains_key(&IVec2::new(5, 0)));
+    }
+
+    #[test]
+    fn should_spawn_the_cells_once_and_recolor_changed_ones() {
+        // given
+        let mut app = App::new();
+        app.add_plugins(GridPlugin);
+        app.add_event::<LevelStarted>();
+        app.init_resource::<MinimapView>();
+        app.init_resource::<Explored>();
+        app.init_resource::<Settings>();
+        app.add_systems(Startup, start_minimap);
+        app.add_systems(Update, draw_minimap);
+        for x in 0..=1 {
+            app.world_mut()
+                .spawn((FloorTile, TileCoordinate::new(x, 0)));
+        }
+        let coin = app
+            .world_mut()
+            .spawn((Gold { coins: 1 }, TileCoordinate::new(1, 0)))
+            .id();
+        // a tutorial letter and a marker above the floor
+        app.world_mut().spawn(TileCoordinate::new(2, 0));
+        app.world_mut().spawn((
+            Hazard { damage: 1 },
+            TileCoordinate::new(0, 0).on(TileLayer::Overlay),
+        ));
+        app.update();
+        let world = app.world_mut();
+        let spawned: Vec<Entity> = world
+            .query_filtered::<Entity, With<MinimapCell>>()
+            .iter(world)
+            .collect();
+
+        // when
+        app.world_mut().despawn(coin);
+        app.update();
+
+        // then
+        let world = app.world_mut();
+        let mut cells = world.query_filtered::<(Entity, &BackgroundColor), With<MinimapCell>>();
+        let kept: Vec<Entity> = cells.iter(world).map(|(e, _)| e).collect();
+        assert_eq!(spawned.len(), 2);
+        assert_eq!(kept, spawned);
+        assert!(cells.iter(world).all(|(_, c)| c.0 == FLOOR_COLOR));
+    }
+}
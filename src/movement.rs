@@ -1,65 +1,82 @@
-use bevy::{app::Plugin, platform::collections::HashMap};
+use std::time::Duration;
+
+use bevy::app::Plugin;
 
 use AppState::Running;
 use bevy::prelude::*;
 
 use crate::{
     app_states::AppState,
-    controls::{Down, Left, PlayerControlled, Right, Up},
-    in_game::LevelFinished,
-    sprites::MoveAnimation,
-    tiles::{InteractableTile, SolidTile, TileCoordinate},
+    controls::{MoveIntent, PlayerControlled},
+    grid::{GridIndex, GridRole},
+    tiles::{Direction, FloorBehaviour, TileCoordinate},
+    tween::{ANIM_DURATION, Tween, TweenTarget, TweenTrack},
 };
 
 // Constants
 const NAME: &str = "movement";
 
+/// longest slide over ice, open levels would slide forever
+const MAX_SLIDE: usize = 64;
+/// seconds between two pushes of a conveyor
+const CONVEYOR_TICK: f32 = 0.5;
+
 // Plugin
 pub struct MovementPlugin;
 
 impl Plugin for MovementPlugin {
     fn build(&self, app: &mut App) {
         app
+            // resources
+            .init_resource::<MoveRules>()
             // events
             .add_event::<MoveTriggered>()
             .add_event::<MoveBlocked>()
+            .add_event::<MoveStalled>()
             .add_event::<InteractionTriggered>()
             // systems
             .add_systems(OnEnter(Running), start_movement)
             .add_systems(
                 Update,
-                (
-                    handle_input,
-                    update_movement,
-                    solid_tiles_added,
-                    solid_tiles_removed,
-                    interaction_tiles_added,
-                    interaction_tiles_removed,
-                )
-                    .run_if(in_state(Running)),
-            )
-            .add_systems(
-                Update,
-                (reset_movement)
-                    .run_if(resource_exists::<SolidTiles>)
-                    .run_if(resource_exists::<InteractionTiles>)
-                    .run_if(on_event::<LevelFinished>),
+                (handle_input, push_on_conveyors, update_movement).run_if(in_state(Running)),
             )
             .add_systems(OnExit(Running), stop_movement);
     }
 }
 
 // Components
+/// stuck in mud, the next move only frees the mover
+#[derive(Component)]
+pub struct Stuck;
 
 // Resources
-#[derive(Resource, Default, Debug)]
-struct SolidTiles {
-    pub map: HashMap<TileCoordinate, Entity>,
+#[derive(Resource)]
+struct ConveyorTick(Timer);
+
+/// how players may move in the current level, levels insert this on start
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MoveRules {
+    pub directions: MoveDirections,
+    pub corners: CornerCutting,
 }
 
-#[derive(Resource, Default, Debug)]
-struct InteractionTiles {
-    pub map: HashMap<TileCoordinate, Entity>,
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MoveDirections {
+    #[default]
+    Four,
+    /// diagonals too
+    Eight,
+}
+
+/// solid tiles on the two cells a diagonal move passes between
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum CornerCutting {
+    /// any solid corner blocks
+    #[default]
+    Never,
+    /// squeezing past one solid corner is fine, two block
+    OneCorner,
 }
 
 // Events
@@ -69,6 +86,8 @@ pub struct MoveTriggered {
     pub mover: Entity,
     pub start: TileCoordinate,
     pub end: TileCoordinate,
+    /// moved by a conveyor instead of by a turn of the player
+    pub pushed: bool,
 }
 
 /// player move blocked by an entity
@@ -79,6 +98,14 @@ pub struct MoveBlocked {
     pub blocked_by: Entity,
 }
 
+/// player spent the move getting out of the mud, it counts as a move
+#[derive(Event)]
+#[allow(dead_code)]
+pub struct MoveStalled {
+    pub mover: Entity,
+    pub at: TileCoordinate,
+}
+
 /// player interacted with an entity
 #[derive(Event)]
 #[allow(dead_code)]
@@ -90,262 +117,457 @@ pub struct InteractionTriggered {
 // Systems
 fn start_movement(mut commands: Commands) {
     debug!("starting {}", NAME);
-    commands.insert_resource(SolidTiles::default());
-    commands.insert_resource(InteractionTiles::default());
+    commands.insert_resource(ConveyorTick(Timer::from_seconds(
+        CONVEYOR_TICK,
+        TimerMode::Repeating,
+    )));
 }
 
 fn update_movement() {
     debug!("updating {}", NAME);
 }
 
-fn solid_tiles_added(
-    mut _commands: Commands,
-    added: Query<(Entity, &TileCoordinate), Added<SolidTile>>,
-    mut solid_tiles: ResMut<SolidTiles>,
-) {
-    debug!("solid tiles added {}", NAME);
-    for (entity, tile_coordinate) in added.iter() {
-        debug!("solid tiles added at {} for {}", tile_coordinate, entity);
-        if let Some(overridden) = solid_tiles.map.insert(tile_coordinate.clone(), entity) {
-            debug!(
-                "solid tiles replaced at {} for {}. old entity: {}",
-                tile_coordinate, entity, overridden
-            );
-            // TODO: cleanup overridden solid tile. not sure how if this is the right place
-            // TODO: following line will crash CountdownTimer event listener
-            //commands.entity(overridden).despawn();
-        }
-    }
-}
-
-fn solid_tiles_removed(
-    mut _commands: Commands,
-    mut removed: RemovedComponents<SolidTile>,
-    mut solid_tiles: ResMut<SolidTiles>,
-) {
-    debug!("solid tiles removed {}", NAME);
-    for entity in removed.read() {
-        solid_tiles.map.retain(|_, e| entity.ne(e));
-    }
-}
-
-fn interaction_tiles_removed(
-    mut _commands: Commands,
-    mut removed: RemovedComponents<InteractableTile>,
-    mut interaction_tiles: ResMut<InteractionTiles>,
-) {
-    debug!("interaction tiles removed {}", NAME);
-    for entity in removed.read() {
-        interaction_tiles.map.retain(|_, e| entity.ne(e));
-    }
-}
-
-fn interaction_tiles_added(
-    mut _commands: Commands,
-    added: Query<(Entity, &TileCoordinate), Added<InteractableTile>>,
-    mut interactables: ResMut<InteractionTiles>,
-) {
-    debug!("interaction tiles added {}", NAME);
-    for (entity, tile_coordinate) in added.iter() {
-        debug!(
-            "interaction tiles added at {} for {}",
-            tile_coordinate, entity
-        );
-        if let Some(overridden) = interactables.map.insert(tile_coordinate.clone(), entity) {
-            debug!(
-                "interaction tiles replaced at {} for {}. old entity: {}",
-                tile_coordinate, entity, overridden
-            );
-            // TODO: cleanup overridden solid tile. not sure how if this is the right place
-            // TODO: following line will crash CountdownTimer event listener
-            //commands.entity(overridden).despawn();
-        }
-    }
-}
-
-/// receive input events and trigger movement and animations here.
-fn handle_input(
+/// receive move intents and trigger movement and animations here.
+/// every intent moves the players it belongs to, players block each other.
+/// ice keeps them sliding, one-way floors block entry and mud costs an extra turn.
+pub fn handle_input(
     mut commands: Commands,
-    mut players: Query<(Entity, &mut TileCoordinate), With<PlayerControlled>>,
-    mut left: EventReader<Left>,
-    mut right: EventReader<Right>,
-    mut up: EventReader<Up>,
-    mut down: EventReader<Down>,
-    solid_blocks: Res<SolidTiles>,
+    mut players: Query<(Entity, &PlayerControlled, &mut TileCoordinate, Has<Stuck>)>,
+    mut intents: EventReader<MoveIntent>,
+    rules: Res<MoveRules>,
+    mut grid: ResMut<GridIndex>,
+    floors: Query<&FloorBehaviour>,
     mut moved: EventWriter<MoveTriggered>,
     mut blocked: EventWriter<MoveBlocked>,
+    mut stalled: EventWriter<MoveStalled>,
     mut interacted: EventWriter<InteractionTriggered>,
-    interaction_tiles: Res<InteractionTiles>,
 ) {
     debug!("handle input {}", NAME);
 
-    for _ in left.read() {
-        debug!("handle left input");
-
-        for (mover, tc) in players.iter_mut() {
-            let start = tc.clone();
-            let mut end = tc.clone();
-            end.x = tc.x - 1;
-
-            if let Some(i) = interaction_tiles.map.get(&end) {
-                debug!("handle left interaction input: {:?}", interaction_tiles.map);
-                interacted.write(InteractionTriggered {
-                    triggered_by: mover,
-                    interacted_with: *i,
-                });
-                debug!("handle left interaction input: event sent");
-            }
-
-            if let Some(b) = solid_blocks.map.get(&end) {
-                debug!("handle left blocking input: {:?}", solid_blocks.map);
-                blocked.write(MoveBlocked {
+    let mut freed = Vec::new();
+    for MoveIntent { player, direction } in intents.read().copied() {
+        debug!("handle input {:?} for {:?}", direction, player);
+        if direction.is_diagonal() && rules.directions == MoveDirections::Four {
+            debug!("ignoring diagonal move in a four-way level");
+            continue;
+        }
+        for (mover, _, mut tc, stuck) in players.iter_mut().filter(|(_, p, _, _)| p.0 == player) {
+            if stuck && !freed.contains(&mover) {
+                debug!("{} spends the move getting out of the mud", mover);
+                commands.entity(mover).remove::<Stuck>();
+                freed.push(mover);
+                stalled.write(MoveStalled {
                     mover,
-                    blocked_by: *b,
+                    at: tc.clone(),
                 });
-                debug!("handle left blocking input: event sent");
-            } else {
-                commands.entity(mover).insert(MoveAnimation {
-                    start: start.clone(),
-                    end: end.clone(),
-                    ..default()
-                });
-                moved.write(MoveTriggered { mover, start, end });
+                continue;
             }
-        }
-    }
-
-    for _ in right.read() {
-        debug!("handle right input");
-
-        for (mover, tc) in players.iter_mut() {
-            let start = tc.clone();
-            let mut end = tc.clone();
-            end.x = tc.x + 1;
 
-            if let Some(i) = interaction_tiles.map.get(&end) {
+            let target = tc.offset(direction.offset());
+            if let Some(i) = grid.first(target.cell(), GridRole::Interactable) {
                 interacted.write(InteractionTriggered {
                     triggered_by: mover,
-                    interacted_with: *i,
+                    interacted_with: i,
                 });
             }
 
-            if let Some(blocked_by) = solid_blocks.map.get(&end) {
-                blocked.write(MoveBlocked {
-                    mover,
-                    blocked_by: *blocked_by,
-                });
-            } else {
-                commands.entity(mover).insert(MoveAnimation {
-                    start: start.clone(),
-                    end: end.clone(),
-                    ..default()
-                });
-                moved.write(MoveTriggered { mover, start, end });
+            let path = resolve_move(&grid, &floors, &rules, mover, &tc, direction);
+            match (path.tiles.is_empty(), path.blocked_by) {
+                (true, Some(blocked_by)) => {
+                    debug!("handle blocking input: {:?}", blocked_by);
+                    blocked.write(MoveBlocked { mover, blocked_by });
+                }
+                (true, None) => (),
+                (false, _) => {
+                    moved.write(finish_move(
+                        &mut commands,
+                        &mut grid,
+                        &floors,
+                        mover,
+                        &mut tc,
+                        &path.tiles,
+                    ));
+                }
             }
         }
     }
+}
 
-    for _ in up.read() {
-        debug!("handle up input");
-
-        for (mover, tc) in players.iter_mut() {
-            let start = tc.clone();
-            let mut end = tc.clone();
-            end.y = tc.y + 1;
-
-            if let Some(i) = interaction_tiles.map.get(&end) {
-                interacted.write(InteractionTriggered {
-                    triggered_by: mover,
-                    interacted_with: *i,
-                });
-            }
-
-            if let Some(blocked_by) = solid_blocks.map.get(&end) {
-                blocked.write(MoveBlocked {
-                    mover,
-                    blocked_by: *blocked_by,
-                });
-            } else {
-                commands.entity(mover).insert(MoveAnimation {
-                    start: start.clone(),
-                    end: end.clone(),
-                    ..default()
-                });
-                moved.write(MoveTriggered { mover, start, end });
-            }
+/// conveyors push the players standing on them one tile per tick
+fn push_on_conveyors(
+    mut commands: Commands,
+    time: Res<Time>,
+    tick: Option<ResMut<ConveyorTick>>,
+    mut players: Query<(Entity, &mut TileCoordinate), With<PlayerControlled>>,
+    mut grid: ResMut<GridIndex>,
+    floors: Query<&FloorBehaviour>,
+    rules: Res<MoveRules>,
+    mut moved: EventWriter<MoveTriggered>,
+) {
+    let Some(mut tick) = tick else {
+        return;
+    };
+    if !tick.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    for (mover, mut tc) in players.iter_mut() {
+        let conveyor = floors_at(&grid, &floors, tc.cell()).find_map(|f| match f {
+            FloorBehaviour::Conveyor(direction) => Some(direction),
+            _ => None,
+        });
+        let Some(direction) = conveyor else {
+            continue;
+        };
+        let path = resolve_move(&grid, &floors, &rules, mover, &tc, direction);
+        if !path.tiles.is_empty() {
+            let mut m = finish_move(
+                &mut commands,
+                &mut grid,
+                &floors,
+                mover,
+                &mut tc,
+                &path.tiles,
+            );
+            m.pushed = true;
+            moved.write(m);
         }
     }
+}
 
-    for _ in down.read() {
-        debug!("handle up input");
-
-        for (mover, tc) in players.iter_mut() {
-            let start = tc.clone();
-            let mut end = tc.clone();
-            end.y = tc.y - 1;
+fn stop_movement(mut commands: Commands) {
+    debug!("stopping {}", NAME);
+    commands.remove_resource::<ConveyorTick>();
+}
 
-            if let Some(i) = interaction_tiles.map.get(&end) {
-                interacted.write(InteractionTriggered {
-                    triggered_by: mover,
-                    interacted_with: *i,
-                });
-            }
+// helper functions
+/// where a move ends, a slide over ice passes several tiles
+struct MovePath {
+    tiles: Vec<TileCoordinate>,
+    blocked_by: Option<Entity>,
+}
 
-            if let Some(blocked_by) = solid_blocks.map.get(&end) {
-                blocked.write(MoveBlocked {
-                    mover,
-                    blocked_by: *blocked_by,
-                });
-            } else {
-                commands.entity(mover).insert(MoveAnimation {
-                    start: start.clone(),
-                    end: end.clone(),
-                    ..default()
-                });
-                moved.write(MoveTriggered { mover, start, end });
-            }
+fn resolve_move(
+    grid: &GridIndex,
+    floors: &Query<&FloorBehaviour>,
+    rules: &MoveRules,
+    mover: Entity,
+    start: &TileCoordinate,
+    direction: Direction,
+) -> MovePath {
+    let mut tiles: Vec<TileCoordinate> = Vec::new();
+    let mut at = start.clone();
+    while tiles.len() < MAX_SLIDE {
+        let next = at.offset(direction.offset());
+        if let Some(blocked_by) = corner_blocker(grid, rules.corners, at.cell(), direction)
+            .or_else(|| blocker(grid, floors, mover, next.cell(), direction))
+        {
+            return MovePath {
+                tiles,
+                blocked_by: Some(blocked_by),
+            };
+        }
+        tiles.push(next.clone());
+        if !floors_at(grid, floors, next.cell()).any(|f| f == FloorBehaviour::Ice) {
+            break;
         }
+        at = next;
     }
+    MovePath {
+        tiles,
+        blocked_by: None,
+    }
+}
+
+/// solid tiles first, then other players, then one-way floors facing elsewhere, then pits
+/// without a platform over them
+pub fn blocker(
+    grid: &GridIndex,
+    floors: &Query<&FloorBehaviour>,
+    mover: Entity,
+    cell: IVec2,
+    direction: Direction,
+) -> Option<Entity> {
+    // players that already moved this frame block on their new cell
+    grid.first(cell, GridRole::Solid)
+        .or_else(|| grid.with_role(cell, GridRole::Player).find(|e| *e != mover))
+        .or_else(|| {
+            grid.with_role(cell, GridRole::Floor).find(
+                |f| matches!(floors.get(*f), Ok(FloorBehaviour::OneWay(d)) if *d != direction),
+            )
+        })
+        .or_else(|| {
+            grid.first(cell, GridRole::Pit)
+                .filter(|_| grid.first(cell, GridRole::Platform).is_none())
+        })
 }
 
-fn reset_movement(mut commands: Commands, mut events: EventReader<LevelFinished>) {
-    for _ in events.read() {
-        commands.insert_resource(SolidTiles::default());
-        commands.insert_resource(InteractionTiles::default());
+/// solid tiles on the corners a diagonal move cuts, depending on the level's rules
+fn corner_blocker(
+    grid: &GridIndex,
+    corners: CornerCutting,
+    from: IVec2,
+    direction: Direction,
+) -> Option<Entity> {
+    let (horizontal, vertical) = direction.corners()?;
+    let solid = |d: Direction| grid.first(from + d.offset(), GridRole::Solid);
+    match corners {
+        CornerCutting::Never => solid(horizontal).or_else(|| solid(vertical)),
+        CornerCutting::OneCorner => solid(horizontal).and(solid(vertical)),
     }
 }
 
-fn stop_movement(mut commands: Commands) {
-    debug!("stopping {}", NAME);
-    commands.remove_resource::<SolidTiles>();
-    commands.remove_resource::<InteractionTiles>();
+fn floors_at<'a>(
+    grid: &'a GridIndex,
+    floors: &'a Query<&FloorBehaviour>,
+    cell: IVec2,
+) -> impl Iterator<Item = FloorBehaviour> + 'a {
+    grid.with_role(cell, GridRole::Floor)
+        .filter_map(|f| floors.get(f).ok().copied())
 }
 
-// helper functions
+/// moves the mover along the path, mud at the end gets it stuck
+fn finish_move(
+    commands: &mut Commands,
+    grid: &mut GridIndex,
+    floors: &Query<&FloorBehaviour>,
+    mover: Entity,
+    tc: &mut TileCoordinate,
+    path: &[TileCoordinate],
+) -> MoveTriggered {
+    let start = tc.clone();
+    let end = path.last().cloned().unwrap_or(start.clone());
+    commands.entity(mover).insert(move_tween(&start, path));
+    grid.place(mover, &end);
+    if floors_at(grid, floors, end.cell()).any(|f| f == FloorBehaviour::Mud) {
+        commands.entity(mover).insert(Stuck);
+    }
+    *tc = end.clone();
+    MoveTriggered {
+        mover,
+        start,
+        end,
+        pushed: false,
+    }
+}
+
+/// one eased step, slides keep their speed between the tiles
+pub fn move_tween(start: &TileCoordinate, path: &[TileCoordinate]) -> Tween {
+    let duration = Duration::from_millis(ANIM_DURATION);
+    if let [end] = path {
+        return Tween::new(TweenTrack::step(
+            TweenTarget::Translation(start.clone().into(), end.clone().into()),
+            duration,
+            EaseFunction::CircularInOut,
+        ));
+    }
+    let mut from = start.clone();
+    let steps = path
+        .iter()
+        .enumerate()
+        .map(|(i, to)| {
+            let ease = match i {
+                0 => EaseFunction::QuadraticIn,
+                i if i + 1 == path.len() => EaseFunction::QuadraticOut,
+                _ => EaseFunction::Linear,
+            };
+            let step = TweenTrack::step(
+                TweenTarget::Translation(from.clone().into(), to.clone().into()),
+                duration / 2,
+                ease,
+            );
+            from = to.clone();
+            step
+        })
+        .collect();
+    Tween::new(TweenTrack::sequence(steps))
+}
 
 // tests
 #[cfg(test)]
 mod tests {
+    use crate::{
+        controls::PlayerId,
+        grid::GridPlugin,
+        tiles::{SolidTile, TileLayer},
+    };
+
     // Note this useful idiom: importing names from outer (for mod tests) scope.
-    //use super::*;
-    //use std::borrow::BorrowMut;
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_event::<MoveIntent>()
+            .init_resource::<MoveRules>()
+            .add_event::<MoveTriggered>()
+            .add_event::<MoveBlocked>()
+            .add_event::<MoveStalled>()
+            .add_event::<InteractionTriggered>();
+        app.add_plugins(GridPlugin);
+        app.add_systems(Update, handle_input);
+        app
+    }
+
+    fn intent(player: PlayerId, direction: Direction) -> MoveIntent {
+        MoveIntent { player, direction }
+    }
 
-    /*
     #[test]
-    fn should_test_something() {
+    fn should_move_only_own_player_and_block_on_other_players() {
         // given
-        //let mut app = App::new();
+        let mut app = app();
+        let one = app
+            .world_mut()
+            .spawn((PlayerControlled(PlayerId::One), TileCoordinate::default()))
+            .id();
+        let two = app
+            .world_mut()
+            .spawn((
+                PlayerControlled(PlayerId::Two),
+                TileCoordinate { x: 2, y: 0, z: 0 },
+            ))
+            .id();
+
+        // when
+        app.world_mut()
+            .send_event(intent(PlayerId::Two, Direction::Left));
+        app.world_mut()
+            .send_event(intent(PlayerId::One, Direction::Right));
+        app.update();
+
+        // then
+        let x = |app: &App, e: Entity| app.world().get::<TileCoordinate>(e).unwrap().x;
+        // intents are handled in the order they were sent
+        assert_eq!(x(&app, two), 1);
+        assert_eq!(x(&app, one), 0);
+        let events = app.world().resource::<Events<MoveBlocked>>();
+        let blocked: Vec<&MoveBlocked> = events.iter_current_update_events().collect();
+        assert_eq!(blocked.len(), 1);
+        assert_eq!((blocked[0].mover, blocked[0].blocked_by), (one, two));
+    }
+
+    #[test]
+    fn should_block_on_solid_tiles_of_any_layer() {
+        // given
+        let mut app = app();
+        let player = app
+            .world_mut()
+            .spawn((
+                PlayerControlled(PlayerId::One),
+                TileCoordinate::new(0, 0).on(TileLayer::Actors),
+            ))
+            .id();
+        let wall = app
+            .world_mut()
+            .spawn((SolidTile, TileCoordinate::new(1, 0).on(TileLayer::Overlay)))
+            .id();
+
+        // when
+        app.world_mut()
+            .send_event(intent(PlayerId::One, Direction::Right));
+        app.update();
+
+        // then
+        assert_eq!(
+            app.world().get::<TileCoordinate>(player).unwrap().cell(),
+            IVec2::ZERO
+        );
+        let events = app.world().resource::<Events<MoveBlocked>>();
+        let blocked: Vec<&MoveBlocked> = events.iter_current_update_events().collect();
+        assert_eq!(blocked.len(), 1);
+        assert_eq!(blocked[0].blocked_by, wall);
+    }
+
+    #[test]
+    fn should_slide_over_ice_until_blocked() {
+        // given
+        let mut app = app();
+        let player = app
+            .world_mut()
+            .spawn((PlayerControlled(PlayerId::One), TileCoordinate::new(0, 0)))
+            .id();
+        for x in 1..=3 {
+            app.world_mut()
+                .spawn((FloorBehaviour::Ice, TileCoordinate::new(x, 0)));
+        }
+        app.world_mut().spawn((
+            FloorBehaviour::OneWay(Direction::Left),
+            TileCoordinate::new(4, 0),
+        ));
+
+        // when
+        app.world_mut()
+            .send_event(intent(PlayerId::One, Direction::Right));
+        app.update();
+
+        // then
+        // slid over the ice, the one-way floor stopped it
+        let coordinate = app.world().get::<TileCoordinate>(player).unwrap();
+        assert_eq!(coordinate.cell(), IVec2::new(3, 0));
+    }
+
+    #[test]
+    fn should_spend_a_move_getting_out_of_the_mud() {
+        // given
+        let mut app = app();
+        let player = app
+            .world_mut()
+            .spawn((PlayerControlled(PlayerId::One), TileCoordinate::new(0, 0)))
+            .id();
+        app.world_mut()
+            .spawn((FloorBehaviour::Ice, TileCoordinate::new(1, 0)));
+        app.world_mut()
+            .spawn((FloorBehaviour::Mud, TileCoordinate::new(2, 0)));
+
+        // when
+        app.world_mut()
+            .send_event(intent(PlayerId::One, Direction::Right));
+        app.update();
+        let stuck = app.world().get::<Stuck>(player).is_some();
+        app.world_mut()
+            .send_event(intent(PlayerId::One, Direction::Left));
+        app.update();
+
+        // then
+        // slid over the ice into the mud, the mud ate the next move
+        let coordinate = app.world().get::<TileCoordinate>(player).unwrap();
+        assert_eq!(coordinate.cell(), IVec2::new(2, 0));
+        assert!(stuck);
+        assert!(app.world().get::<Stuck>(player).is_none());
+        let events = app.world().resource::<Events<MoveStalled>>();
+        assert_eq!(events.iter_current_update_events().count(), 1);
+    }
+
+    #[test]
+    fn should_cut_corners_by_the_level_rules() {
+        // given
+        let mut app = app();
+        app.insert_resource(MoveRules {
+            directions: MoveDirections::Eight,
+            corners: CornerCutting::OneCorner,
+        });
+        let player = app
+            .world_mut()
+            .spawn((PlayerControlled(PlayerId::One), TileCoordinate::new(0, 0)))
+            .id();
+        for (x, y) in [(1, 0), (1, 2), (0, 3)] {
+            app.world_mut()
+                .spawn((SolidTile, TileCoordinate::new(x, y)));
+        }
 
         // when
-        //app.add_event::<HealthDamageReceived>();
-        //app.add_systems(Update, damage_received_listener);
-        //let entity = app.borrow_mut().world().spawn(Health(100)).id();
-        //app.borrow_mut().world().resource_mut::<Events<HealthDamageReceived>>().send(HealthDamageReceived { entity, damage: 10 });
-        //app.update();
+        // past one solid corner, then between two
+        app.world_mut()
+            .send_event(intent(PlayerId::One, Direction::UpRight));
+        app.update();
+        app.world_mut()
+            .send_event(intent(PlayerId::One, Direction::UpLeft));
+        app.update();
+        app.world_mut()
+            .send_event(intent(PlayerId::One, Direction::UpRight));
+        app.update();
 
         // then
-        //assert!(app.world().get::<Health>(entity).is_some());
-        //assert_eq!(app.world().get::<Health>(entity).unwrap().0, 90);
+        let coordinate = app.world().get::<TileCoordinate>(player).unwrap();
+        assert_eq!(coordinate.cell(), IVec2::new(0, 2));
     }
-    */
 }
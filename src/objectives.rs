@@ -0,0 +1,483 @@
+use std::time::Duration;
+
+use bevy::{app::Plugin, platform::collections::HashSet};
+
+use AppState::Running;
+use bevy::prelude::*;
+
+use crate::{
+    app_states::AppState,
+    gold::PlayerPickedUpGoldCoins,
+    grid::{GridIndex, GridRole},
+    health::{Hearts, PickedUpHearts},
+    movement::{InteractionTriggered, MoveStalled, MoveTriggered},
+    sprites::ExfilSprite,
+    tiles::TriggerTile,
+    tween::{Tween, TweenTarget, TweenTrack},
+};
+
+// Constants
+const NAME: &str = "objectives";
+
+const DONE_COLOR: Color = Color::srgb(0.35, 0.75, 0.35);
+const OPEN_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const FAILED_COLOR: Color = Color::srgb(0.75, 0.35, 0.35);
+const UNLOCK_FLASH_MILLIS: u64 = 400;
+
+// Plugin
+pub struct ObjectivesPlugin;
+
+impl Plugin for ObjectivesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(Running), start_objectives)
+            .add_systems(
+                Update,
+                (
+                    track_gold,
+                    track_hearts,
+                    track_levers,
+                    track_moves,
+                    lock_exits,
+                    update_objectives_ui,
+                )
+                    .chain()
+                    .run_if(in_state(Running))
+                    .run_if(resource_exists::<LevelObjectives>),
+            )
+            .add_systems(OnExit(Running), stop_objectives);
+    }
+}
+
+// Components
+/// exits with this component can not be used yet
+#[derive(Component)]
+pub struct ExitLocked;
+
+#[derive(Component)]
+struct ObjectivesUI;
+
+#[derive(Component)]
+struct ObjectivesUIText;
+
+// Resources
+/// objectives of the current level. levels insert this on start, replacing the previous one.
+#[derive(Resource, Debug, Default)]
+pub struct LevelObjectives {
+    pub objectives: Vec<Objective>,
+    levers_flipped: HashSet<Entity>,
+}
+
+impl LevelObjectives {
+    pub fn new(objectives: Vec<Objective>) -> Self {
+        LevelObjectives {
+            objectives,
+            ..default()
+        }
+    }
+
+    /// exits open once every required objective is done, move limits only have to hold
+    pub fn exit_unlocked(&self) -> bool {
+        self.objectives
+            .iter()
+            .filter(|o| o.required && o.kind != ObjectiveKind::ReachExit)
+            .all(|o| match o.kind {
+                ObjectiveKind::FinishUnderMoves(_) => o.state() != ObjectiveState::Failed,
+                _ => o.state() == ObjectiveState::Done,
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Objective {
+    pub kind: ObjectiveKind,
+    /// required objectives lock the exit until they are done
+    pub required: bool,
+    pub progress: i64,
+    pub target: i64,
+    /// a player reached the exit, move limits are decided then
+    pub finished: bool,
+}
+
+impl Objective {
+    pub fn required(kind: ObjectiveKind) -> Self {
+        Objective {
+            target: kind.target(),
+            kind,
+            required: true,
+            progress: 0,
+            finished: false,
+        }
+    }
+
+    pub fn optional(kind: ObjectiveKind) -> Self {
+        Objective {
+            required: false,
+            ..Objective::required(kind)
+        }
+    }
+
+    pub fn state(&self) -> ObjectiveState {
+        match self.kind {
+            ObjectiveKind::FinishUnderMoves(moves) if self.progress > moves as i64 => {
+                ObjectiveState::Failed
+            }
+            ObjectiveKind::FinishUnderMoves(_) if self.finished => ObjectiveState::Done,
+            ObjectiveKind::FinishUnderMoves(_) => ObjectiveState::Open,
+            // a target of 0 leaves nothing to do
+            _ if self.progress >= self.target => ObjectiveState::Done,
+            _ => ObjectiveState::Open,
+        }
+    }
+
+    fn description(&self) -> String {
+        match self.kind {
+            ObjectiveKind::CollectGold(coins) => {
+                format!("Collect {} gold ({}/{})", coins, self.progress, coins)
+            }
+            ObjectiveKind::PickUpAllHearts => {
+                format!("Pick up all hearts ({}/{})", self.progress, self.target)
+            }
+            ObjectiveKind::FlipAllLevers => {
+                format!("Flip every lever ({}/{})", self.progress, self.target)
+            }
+            ObjectiveKind::FinishUnderMoves(moves) => {
+                format!(
+                    "Finish within {} moves ({}/{})",
+                    moves, self.progress, moves
+                )
+            }
+            ObjectiveKind::ReachExit => "Reach the exit".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectiveKind {
+    CollectGold(i64),
+    PickUpAllHearts,
+    FlipAllLevers,
+    /// at most this many moves
+    FinishUnderMoves(u32),
+    ReachExit,
+}
+
+impl ObjectiveKind {
+    /// initial target, counting objectives find their target in the level
+    fn target(&self) -> i64 {
+        match self {
+            ObjectiveKind::CollectGold(coins) => *coins,
+            ObjectiveKind::FinishUnderMoves(moves) => *moves as i64,
+            ObjectiveKind::ReachExit => 1,
+            ObjectiveKind::PickUpAllHearts | ObjectiveKind::FlipAllLevers => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectiveState {
+    Open,
+    Done,
+    Failed,
+}
+
+// Events
+
+// Systems
+fn start_objectives(mut _commands: Commands) {
+    debug!("starting {}", NAME);
+}
+
+fn track_gold(
+    mut objectives: ResMut<LevelObjectives>,
+    mut picked_up: EventReader<PlayerPickedUpGoldCoins>,
+) {
+    for event in picked_up.read() {
+        for o in objectives.objectives.iter_mut() {
+            if let ObjectiveKind::CollectGold(_) = o.kind {
+                o.progress += event.coins.coins;
+            }
+        }
+    }
+}
+
+fn track_hearts(
+    mut objectives: ResMut<LevelObjectives>,
+    mut picked_up: EventReader<PickedUpHearts>,
+    hearts: Query<(), With<Hearts>>,
+) {
+    let picked_up = picked_up.read().count() as i64;
+    // hearts can spawn during the level, so the target follows the hearts left to pick up
+    let remaining = hearts.iter().count() as i64;
+    let changed = objectives.objectives.iter().any(|o| {
+        o.kind == ObjectiveKind::PickUpAllHearts
+            && (picked_up > 0 || o.target != o.progress + remaining)
+    });
+    if !changed {
+        return;
+    }
+
+    for o in objectives.objectives.iter_mut() {
+        if o.kind == ObjectiveKind::PickUpAllHearts {
+            o.progress += picked_up;
+            o.target = o.progress + remaining;
+        }
+    }
+}
+
+fn track_levers(
+    mut objectives: ResMut<LevelObjectives>,
+    mut triggered: EventReader<InteractionTriggered>,
+    levers: Query<Entity, With<TriggerTile>>,
+) {
+    let flipped: HashSet<Entity> = triggered
+        .read()
+        .filter(|t| levers.contains(t.interacted_with))
+        .map(|t| t.interacted_with)
+        .filter(|lever| !objectives.levers_flipped.contains(lever))
+        .collect();
+    let progress = (objectives.levers_flipped.len() + flipped.len()) as i64;
+    let target = (levers.iter().count() as i64).max(progress);
+    let changed = objectives.objectives.iter().any(|o| {
+        o.kind == ObjectiveKind::FlipAllLevers && (o.progress != progress || o.target != target)
+    });
+    if !changed {
+        return;
+    }
+
+    let objectives = objectives.as_mut();
+    objectives.levers_flipped.extend(flipped);
+    for o in objectives.objectives.iter_mut() {
+        if o.kind == ObjectiveKind::FlipAllLevers {
+            o.progress = progress;
+            o.target = target;
+        }
+    }
+}
+
+fn track_moves(
+    mut objectives: ResMut<LevelObjectives>,
+    mut moves: EventReader<MoveTriggered>,
+    mut stalled: EventReader<MoveStalled>,
+    exits: Query<(), (With<ExfilSprite>, Without<ExitLocked>)>,
+    grid: Res<GridIndex>,
+) {
+    let stalled = stalled.read().count() as i64;
+    for o in objectives.objectives.iter_mut() {
+        if let ObjectiveKind::FinishUnderMoves(_) = o.kind {
+            o.progress += stalled;
+        }
+    }
+    for m in moves.read() {
+        let reached_exit = grid
+            .with_role(m.end.cell(), GridRole::Exit)
+            .any(|e| exits.contains(e));
+        for o in objectives.objectives.iter_mut() {
+            match o.kind {
+                ObjectiveKind::FinishUnderMoves(_) => {
+                    if !m.pushed {
+                        o.progress += 1;
+                    }
+                    o.finished |= reached_exit;
+                }
+                ObjectiveKind::ReachExit if reached_exit => o.progress = 1,
+                _ => (),
+            }
+        }
+    }
+}
+
+fn lock_exits(
+    mut commands: Commands,
+    objectives: Res<LevelObjectives>,
+    exits: Query<(Entity, Has<ExitLocked>, Option<&Sprite>), With<ExfilSprite>>,
+) {
+    let unlocked = objectives.exit_unlocked();
+    for (exit, locked, sprite) in exits.iter() {
+        if unlocked && locked {
+            debug!("unlocking exit {}", exit);
+            commands.entity(exit).remove::<ExitLocked>();
+            if let Some(sprite) = sprite {
+                // flash the exit so the player notices it opened
+                commands.entity(exit).insert(Tween::new(TweenTrack::step(
+                    TweenTarget::SpriteColor(Color::WHITE, sprite.color),
+                    Duration::from_millis(UNLOCK_FLASH_MILLIS),
+                    EaseFunction::QuadraticIn,
+                )));
+            }
+        } else if !unlocked && !locked {
+            debug!("locking exit {}", exit);
+            commands.entity(exit).insert(ExitLocked);
+        }
+    }
+}
+
+fn update_objectives_ui(
+    mut commands: Commands,
+    objectives: Res<LevelObjectives>,
+    ui: Query<Entity, With<ObjectivesUI>>,
+) {
+    if !objectives.is_changed() {
+        return;
+    }
+    for root in ui.iter() {
+        commands.entity(root).despawn();
+    }
+    if objectives.objectives.is_empty() {
+        return;
+    }
+
+    let exit_locked = !objectives.exit_unlocked();
+    commands
+        .spawn(objectives_ui_root())
+        .with_children(|parent| {
+            for o in objectives.objectives.iter() {
+                let (marker, color) = match o.state() {
+                    ObjectiveState::Done => ("[x]", DONE_COLOR),
+                    ObjectiveState::Open => ("[ ]", OPEN_COLOR),
+                    ObjectiveState::Failed => ("[-]", FAILED_COLOR),
+                };
+                let optional = if o.required { "" } else { " (optional)" };
+                parent.spawn((
+                    ObjectivesUIText,
+                    Text::new(format!("{} {}{}", marker, o.description(), optional)),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(color),
+                ));
+            }
+            if exit_locked {
+                parent.spawn((
+                    ObjectivesUIText,
+                    Text::new("Exit locked"),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(FAILED_COLOR),
+                ));
+            }
+        });
+}
+
+fn stop_objectives(mut commands: Commands, ui: Query<Entity, With<ObjectivesUI>>) {
+    debug!("stopping {}", NAME);
+    for root in ui.iter() {
+        commands.entity(root).despawn();
+    }
+    commands.remove_resource::<LevelObjectives>();
+}
+
+// helper functions
+fn objectives_ui_root() -> impl Bundle + use<> {
+    (
+        ObjectivesUI,
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(12.0),
+            top: Val::Px(12.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            ..default()
+        },
+    )
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn should_lock_exit_until_required_objectives_are_done() {
+        // given
+        let mut objectives = LevelObjectives::new(vec![
+            Objective::required(ObjectiveKind::CollectGold(5)),
+            Objective::optional(ObjectiveKind::FinishUnderMoves(3)),
+            Objective::required(ObjectiveKind::ReachExit),
+        ]);
+
+        // when
+        let locked_before = !objectives.exit_unlocked();
+        objectives.objectives[0].progress = 5;
+        objectives.objectives[1].progress = 10;
+
+        // then
+        assert!(locked_before);
+        assert!(objectives.exit_unlocked());
+        assert_eq!(objectives.objectives[1].state(), ObjectiveState::Failed);
+    }
+
+    #[test]
+    fn should_unlock_exit_without_hearts_or_levers_to_do() {
+        // given
+        let mut objectives = LevelObjectives::new(vec![
+            Objective::required(ObjectiveKind::PickUpAllHearts),
+            Objective::required(ObjectiveKind::FlipAllLevers),
+        ]);
+
+        // when
+        let unlocked_without_any = objectives.exit_unlocked();
+        objectives.objectives[0].target = 2;
+        objectives.objectives[0].progress = 1;
+
+        // then
+        assert!(unlocked_without_any);
+        assert_eq!(objectives.objectives[1].state(), ObjectiveState::Done);
+        assert!(!objectives.exit_unlocked());
+    }
+
+    #[test]
+    fn should_unlock_exit_while_under_the_required_moves() {
+        // given
+        let mut objectives = LevelObjectives::new(vec![Objective::required(
+            ObjectiveKind::FinishUnderMoves(3),
+        )]);
+
+        // when
+        let unlocked_at_start = objectives.exit_unlocked();
+        let state_at_start = objectives.objectives[0].state();
+        objectives.objectives[0].progress = 3;
+        let unlocked_at_limit = objectives.exit_unlocked();
+        objectives.objectives[0].finished = true;
+        let state_at_exit = objectives.objectives[0].state();
+        objectives.objectives[0].progress = 4;
+
+        // then
+        assert!(unlocked_at_start);
+        assert_eq!(state_at_start, ObjectiveState::Open);
+        assert!(unlocked_at_limit);
+        assert_eq!(state_at_exit, ObjectiveState::Done);
+        assert!(!objectives.exit_unlocked());
+    }
+
+    #[test]
+    fn should_track_gold_pickups() {
+        // given
+        let mut app = App::new();
+        app.add_event::<PlayerPickedUpGoldCoins>();
+        app.add_systems(Update, track_gold);
+        app.insert_resource(LevelObjectives::new(vec![Objective::required(
+            ObjectiveKind::CollectGold(6),
+        )]));
+        let player = app.world_mut().spawn_empty().id();
+
+        // when
+        for coins in [1, 5] {
+            app.world_mut()
+                .resource_mut::<Events<PlayerPickedUpGoldCoins>>()
+                .send(PlayerPickedUpGoldCoins {
+                    player,
+                    coins: crate::gold::Gold { coins },
+                });
+        }
+        app.update();
+
+        // then
+        let objectives = app.world().resource::<LevelObjectives>();
+        assert_eq!(objectives.objectives[0].progress, 6);
+        assert_eq!(objectives.objectives[0].state(), ObjectiveState::Done);
+    }
+}
@@ -0,0 +1,301 @@
+use bevy::app::Plugin;
+
+use AppState::Running;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    app_states::AppState,
+    controls::{PlayerControlled, controls_unlocked},
+    grid::{GridIndex, GridRole},
+    health::HealthDamageReceived,
+    movement::{MoveTriggered, blocker, move_tween},
+    tiles::{Direction, FloorBehaviour, TileCoordinate},
+};
+
+// Constants
+const NAME: &str = "paths";
+
+// Plugin
+pub struct PathsPlugin;
+
+impl Plugin for PathsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (follow_paths, hurt_on_contact)
+                .chain()
+                .run_if(in_state(Running))
+                // paths wait for dialogues and transitions like the players do
+                .run_if(controls_unlocked),
+        );
+    }
+}
+
+// Components
+/// walks a looping path of waypoints, one tile per beat
+#[derive(Component, Debug, Clone)]
+pub struct PathFollower {
+    waypoints: Vec<IVec2>,
+    next: usize,
+    beat: Timer,
+}
+
+impl PathFollower {
+    pub fn new(path: &TilePath) -> Self {
+        PathFollower {
+            waypoints: path
+                .waypoints
+                .iter()
+                .map(|(x, y)| IVec2::new(*x, *y))
+                .collect(),
+            next: 0,
+            beat: Timer::from_seconds(path.beat, TimerMode::Repeating),
+        }
+    }
+
+    /// one tile toward the next waypoint, horizontal first. reached waypoints are skipped.
+    fn step(&mut self, cell: IVec2) -> Option<IVec2> {
+        for _ in 0..self.waypoints.len() {
+            let towards = self.waypoints[self.next] - cell;
+            if towards.x != 0 {
+                return Some(IVec2::new(towards.x.signum(), 0));
+            }
+            if towards.y != 0 {
+                return Some(IVec2::new(0, towards.y.signum()));
+            }
+            self.next = (self.next + 1) % self.waypoints.len();
+        }
+        None
+    }
+}
+
+/// hurts players on the same cell, once per contact
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Hazard {
+    pub damage: usize,
+}
+
+/// shoves players out of its way, waits when they can't move on
+#[derive(Component)]
+pub struct Pusher;
+
+/// carries the players standing on it, they can stand over pits on it
+#[derive(Component)]
+pub struct Platform;
+
+/// path of a [PathFollower] in level data
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TilePath {
+    /// cells to walk to in order, back to the first after the last
+    pub waypoints: Vec<(i32, i32)>,
+    /// seconds per tile
+    pub beat: f32,
+}
+
+// Resources
+
+// Events
+
+// Systems
+fn follow_paths(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut followers: Query<
+        (
+            Entity,
+            &mut PathFollower,
+            &mut TileCoordinate,
+            Has<Pusher>,
+            Has<Platform>,
+        ),
+        Without<PlayerControlled>,
+    >,
+    mut players: Query<&mut TileCoordinate, (With<PlayerControlled>, Without<PathFollower>)>,
+    floors: Query<&FloorBehaviour>,
+    mut grid: ResMut<GridIndex>,
+    mut moved: EventWriter<MoveTriggered>,
+) {
+    debug!("updating {}", NAME);
+    for (follower, mut path, mut tc, pusher, platform) in followers.iter_mut() {
+        if !path.beat.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let Some(offset) = path.step(tc.cell()) else {
+            continue;
+        };
+        let Some(direction) = Direction::from_offset(offset) else {
+            continue;
+        };
+        let next = tc.offset(offset);
+
+        // blocked followers wait for the next beat
+        if grid
+            .with_role(next.cell(), GridRole::Solid)
+            .any(|e| e != follower)
+        {
+            continue;
+        }
+        let ahead: Vec<Entity> = grid.with_role(next.cell(), GridRole::Player).collect();
+        if pusher && !ahead.is_empty() {
+            let stuck = ahead.iter().any(|player| {
+                blocker(&grid, &floors, *player, next.cell() + offset, direction).is_some()
+            });
+            if stuck {
+                continue;
+            }
+            for player in ahead {
+                move_player(
+                    &mut commands,
+                    &mut grid,
+                    &mut players,
+                    &mut moved,
+                    player,
+                    offset,
+                );
+            }
+        } else if platform && !ahead.is_empty() {
+            continue;
+        }
+        let carried: Vec<Entity> = if platform {
+            grid.with_role(tc.cell(), GridRole::Player).collect()
+        } else {
+            Vec::new()
+        };
+
+        commands
+            .entity(follower)
+            .insert(move_tween(&tc, std::slice::from_ref(&next)));
+        grid.place(follower, &next);
+        *tc = next;
+        for player in carried {
+            move_player(
+                &mut commands,
+                &mut grid,
+                &mut players,
+                &mut moved,
+                player,
+                offset,
+            );
+        }
+    }
+}
+
+/// damages players that meet a hazard, whoever moved into whom
+fn hurt_on_contact(
+    grid: Res<GridIndex>,
+    players: Query<(Entity, &TileCoordinate), With<PlayerControlled>>,
+    hazards: Query<&Hazard>,
+    mut contacts: Local<Vec<(Entity, Entity)>>,
+    mut damaged: EventWriter<HealthDamageReceived>,
+) {
+    let mut current = Vec::new();
+    for (player, coordinate) in players.iter() {
+        for hazard in grid.with_role(coordinate.cell(), GridRole::Hazard) {
+            let Ok(h) = hazards.get(hazard) else {
+                continue;
+            };
+            if !contacts.contains(&(player, hazard)) {
+                debug!("{} hit by hazard {}", player, hazard);
+                damaged.write(HealthDamageReceived {
+                    entity: player,
+                    damage: h.damage,
+                });
+            }
+            current.push((player, hazard));
+        }
+    }
+    *contacts = current;
+}
+
+// helper functions
+fn move_player(
+    commands: &mut Commands,
+    grid: &mut GridIndex,
+    players: &mut Query<&mut TileCoordinate, (With<PlayerControlled>, Without<PathFollower>)>,
+    moved: &mut EventWriter<MoveTriggered>,
+    player: Entity,
+    offset: IVec2,
+) {
+    let Ok(mut tc) = players.get_mut(player) else {
+        return;
+    };
+    let start = tc.clone();
+    let end = tc.offset(offset);
+    commands
+        .entity(player)
+        .insert(move_tween(&start, std::slice::from_ref(&end)));
+    grid.place(player, &end);
+    *tc = end.clone();
+    moved.write(MoveTriggered {
+        mover: player,
+        start,
+        end,
+        pushed: true,
+    });
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{controls::PlayerId, grid::GridPlugin, tiles::Pit};
+
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn should_walk_the_waypoints_in_a_loop() {
+        // given
+        let mut follower = PathFollower::new(&TilePath {
+            waypoints: vec![(0, 0), (2, 1)],
+            beat: 1.0,
+        });
+
+        // when
+        let steps: Vec<IVec2> = [(0, 0), (1, 0), (2, 0), (2, 1)]
+            .into_iter()
+            .map(|(x, y)| follower.step(IVec2::new(x, y)).unwrap())
+            .collect();
+
+        // then
+        assert_eq!(steps, vec![IVec2::X, IVec2::X, IVec2::Y, IVec2::NEG_X]);
+    }
+
+    #[test]
+    fn should_carry_players_over_pits() {
+        // given
+        let mut app = App::new();
+        app.add_plugins(GridPlugin);
+        app.add_event::<MoveTriggered>();
+        app.init_resource::<Time>();
+        app.add_systems(Update, follow_paths);
+        let path = TilePath {
+            waypoints: vec![(0, 0), (2, 0)],
+            beat: 1.0,
+        };
+        for x in 0..=2 {
+            app.world_mut().spawn((Pit, TileCoordinate::new(x, 0)));
+        }
+        app.world_mut().spawn((
+            Platform,
+            PathFollower::new(&path),
+            TileCoordinate::new(0, 0),
+        ));
+        let player = app
+            .world_mut()
+            .spawn((PlayerControlled(PlayerId::One), TileCoordinate::new(0, 0)))
+            .id();
+
+        // when
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        app.update();
+
+        // then
+        let coordinate = app.world().get::<TileCoordinate>(player).unwrap();
+        assert_eq!(coordinate.cell(), IVec2::new(1, 0));
+    }
+}
@@ -0,0 +1,215 @@
+use std::time::Duration;
+
+use bevy::{app::Plugin, input::InputSystem};
+
+use AppState::Running;
+use bevy::prelude::*;
+
+use crate::{
+    app_states::{AppState, PauseState},
+    controls::{ControlLock, ControlLocks},
+    transition::StartTransition,
+    tween::{Tween, TweenTarget, TweenTrack},
+};
+
+// Constants
+const NAME: &str = "pause";
+
+const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
+const PRESSED_BUTTON: Color = Color::srgb(0.35, 0.75, 0.35);
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const BACKDROP_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+const FADE_IN_MILLIS: u64 = 150;
+
+// Plugin
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // runs before the dialogue gets to handle escape, so closing a dialogue doesn't pause
+            .add_systems(
+                PreUpdate,
+                (toggle_pause).after(InputSystem).run_if(in_state(Running)),
+            )
+            .add_systems(OnExit(PauseState::Unpaused), pause)
+            .add_systems(OnEnter(PauseState::Unpaused), resume)
+            .add_systems(OnExit(Running), resume)
+            .add_systems(OnEnter(PauseState::Paused), start_pause_menu)
+            .add_systems(
+                Update,
+                (update_pause_menu).run_if(in_state(PauseState::Paused)),
+            )
+            .add_systems(OnExit(PauseState::Paused), stop_pause_menu);
+    }
+}
+
+// Components
+#[derive(Component)]
+struct PauseMenu;
+
+#[derive(Component, Debug, Clone, Copy)]
+enum PauseButton {
+    Resume,
+    Settings,
+    MainMenu,
+}
+
+// Resources
+
+// Events
+
+// Systems
+fn toggle_pause(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<PauseState>>,
+    mut next_state: ResMut<NextState<PauseState>>,
+    locks: Res<ControlLocks>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    match state.get() {
+        // other locks like an open dialogue use escape themselves
+        PauseState::Unpaused if locks.0.is_empty() => next_state.set(PauseState::Paused),
+        PauseState::Unpaused => (),
+        PauseState::Paused => next_state.set(PauseState::Unpaused),
+        PauseState::Settings => next_state.set(PauseState::Paused),
+    }
+}
+
+fn pause(mut time: ResMut<Time<Virtual>>, mut locks: ResMut<ControlLocks>) {
+    debug!("pausing {}", NAME);
+    time.pause();
+    locks.0.insert(ControlLock::Pause);
+}
+
+fn resume(mut time: ResMut<Time<Virtual>>, mut locks: ResMut<ControlLocks>) {
+    debug!("resuming {}", NAME);
+    time.unpause();
+    locks.0.remove(&ControlLock::Pause);
+}
+
+fn start_pause_menu(mut commands: Commands) {
+    debug!("starting {}", NAME);
+    commands
+        .spawn((
+            PauseMenu,
+            Name::new("Pause Menu"),
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor(Color::NONE),
+            GlobalZIndex(10),
+            // the game time stands still while paused
+            Tween::new(TweenTrack::step(
+                TweenTarget::BackgroundColor(Color::NONE, BACKDROP_COLOR),
+                Duration::from_millis(FADE_IN_MILLIS),
+                EaseFunction::QuadraticOut,
+            ))
+            .in_real_time(),
+        ))
+        .with_children(|builder| {
+            builder.spawn((
+                Text::new("Paused"),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+            ));
+            for (text, button) in [
+                ("Resume", PauseButton::Resume),
+                ("Settings", PauseButton::Settings),
+                ("Main Menu", PauseButton::MainMenu),
+            ] {
+                builder
+                    .spawn((
+                        Button,
+                        button,
+                        Node {
+                            width: Val::Px(200.0),
+                            padding: UiRect::all(Val::Px(8.0)),
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        BackgroundColor(NORMAL_BUTTON),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((Text::new(text), TextColor(TEXT_COLOR)));
+                    });
+            }
+        });
+}
+
+fn update_pause_menu(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &PauseButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut next_state: ResMut<NextState<PauseState>>,
+    mut transition: EventWriter<StartTransition>,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                debug!("pause button pressed: {:?}", button);
+                *color = PRESSED_BUTTON.into();
+                match button {
+                    PauseButton::Resume => next_state.set(PauseState::Unpaused),
+                    PauseButton::Settings => next_state.set(PauseState::Settings),
+                    PauseButton::MainMenu => {
+                        transition.write(StartTransition::state(AppState::MainMenu));
+                    }
+                }
+            }
+            Interaction::Hovered => *color = HOVERED_BUTTON.into(),
+            Interaction::None => *color = NORMAL_BUTTON.into(),
+        }
+    }
+}
+
+fn stop_pause_menu(mut commands: Commands, menus: Query<Entity, With<PauseMenu>>) {
+    debug!("stopping {}", NAME);
+    for menu in menus.iter() {
+        commands.entity(menu).despawn();
+    }
+}
+
+// helper functions
+
+// tests
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn should_pause_virtual_time_and_lock_controls() {
+        // given
+        let mut app = App::new();
+        app.init_resource::<Time<Virtual>>();
+        app.init_resource::<ControlLocks>();
+        app.add_systems(Update, pause);
+
+        // when
+        app.update();
+
+        // then
+        assert!(app.world().resource::<Time<Virtual>>().is_paused());
+        assert!(
+            app.world()
+                .resource::<ControlLocks>()
+                .0
+                .contains(&ControlLock::Pause)
+        );
+    }
+}
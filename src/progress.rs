@@ -0,0 +1,105 @@
+use std::collections::BTreeSet;
+
+use bevy::app::Plugin;
+
+use AppState::Running;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_states::{AppState, LevelState},
+    in_game::ExitReached,
+    storage,
+};
+
+// Constants
+const NAME: &str = "progress";
+const PROGRESS_FILE: &str = "progress.ron";
+
+// Plugin
+pub struct ProgressPlugin;
+
+impl Plugin for ProgressPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // resources
+            .insert_resource(storage::load::<LevelProgress>(PROGRESS_FILE))
+            // systems
+            .add_systems(OnEnter(Running), start_progress)
+            .add_systems(
+                Update,
+                (unlock_next_level)
+                    .run_if(in_state(Running))
+                    .run_if(on_event::<ExitReached>),
+            )
+            .add_systems(OnExit(Running), stop_progress);
+    }
+}
+
+// Components
+
+// Resources
+/// unlocked levels, persisted between runs
+#[derive(Resource, Debug, Serialize, Deserialize)]
+pub struct LevelProgress {
+    pub unlocked: BTreeSet<LevelState>,
+}
+
+impl Default for LevelProgress {
+    fn default() -> Self {
+        LevelProgress {
+            unlocked: BTreeSet::from([LevelState::default()]),
+        }
+    }
+}
+
+impl LevelProgress {
+    pub fn is_unlocked(&self, level: &LevelState) -> bool {
+        self.unlocked.contains(level)
+    }
+}
+
+// Events
+
+// Systems
+fn start_progress(mut _commands: Commands) {
+    debug!("starting {}", NAME);
+}
+
+fn unlock_next_level(mut exits: EventReader<ExitReached>, mut progress: ResMut<LevelProgress>) {
+    let mut changed = false;
+    for exit in exits.read() {
+        if let Some(next) = exit.0.next() {
+            changed |= progress.unlocked.insert(next);
+        }
+    }
+    if changed {
+        debug!("unlocked levels: {:?}", progress.unlocked);
+        storage::save(PROGRESS_FILE, progress.as_ref());
+    }
+}
+
+fn stop_progress(mut _commands: Commands) {
+    debug!("stopping {}", NAME);
+}
+
+// helper functions
+
+// tests
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn should_start_with_first_level_unlocked() {
+        // given
+        let progress = LevelProgress::default();
+
+        // then
+        assert!(progress.is_unlocked(&LevelState::Level01));
+        assert!(!progress.is_unlocked(&LevelState::Level02));
+        assert_eq!(LevelState::Level01.next(), Some(LevelState::Level02));
+        assert_eq!(LevelState::Level05.next(), None);
+    }
+}
@@ -0,0 +1,316 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use bevy::{app::Plugin, time::Stopwatch};
+
+use AppState::Running;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_states::{AppState, LevelState},
+    controls::controls_unlocked,
+    in_game::{ExitReached, LevelStarted},
+    movement::{MoveBlocked, MoveStalled, MoveTriggered},
+    storage,
+};
+
+// Constants
+const NAME: &str = "score";
+const BEST_RESULTS_FILE: &str = "best_results.ron";
+const RESULT_CARD_SECONDS: f32 = 3.0;
+
+// Plugin
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // events
+            .add_event::<LevelScored>()
+            // resources
+            .insert_resource(storage::load::<BestResults>(BEST_RESULTS_FILE))
+            // systems
+            .add_systems(OnEnter(Running), start_score)
+            .add_systems(
+                Update,
+                (
+                    reset_stats,
+                    count_moves,
+                    // transitions and dialogues don't count as playing time
+                    tick_level_timer.run_if(controls_unlocked),
+                    score_level,
+                    show_result_card,
+                )
+                    .chain()
+                    .run_if(in_state(Running)),
+            )
+            // the last level exits to the main menu, so the card has to outlive the run
+            .add_systems(Update, hide_result_card)
+            .add_systems(OnExit(Running), stop_score);
+    }
+}
+
+// Components
+#[derive(Component)]
+struct ResultCard(Timer);
+
+// Resources
+/// moves and time of the current level
+#[derive(Resource, Default, Debug)]
+pub struct LevelStats {
+    pub moves: u32,
+    pub blocked: u32,
+    pub timer: Stopwatch,
+}
+
+/// par of a level, levels insert this on start
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct LevelPar {
+    pub moves: u32,
+    pub seconds: f32,
+}
+
+/// best result per level, persisted between runs
+#[derive(Resource, Default, Debug, Serialize, Deserialize)]
+pub struct BestResults(pub BTreeMap<LevelState, LevelResult>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LevelResult {
+    pub stars: u8,
+    pub moves: u32,
+    pub seconds: f32,
+}
+
+impl LevelResult {
+    /// one star for finishing, one for staying within the par moves and one for the par time.
+    /// bumping into walls counts as a move.
+    pub fn new(stats: &LevelStats, par: Option<&LevelPar>) -> Self {
+        let moves = stats.moves + stats.blocked;
+        let seconds = stats.timer.elapsed_secs();
+        let stars = match par {
+            Some(par) => 1 + (moves <= par.moves) as u8 + (seconds <= par.seconds) as u8,
+            None => 1,
+        };
+        LevelResult {
+            stars,
+            moves,
+            seconds,
+        }
+    }
+
+    /// more stars win, then fewer moves, then less time
+    pub fn is_better_than(&self, other: &LevelResult) -> bool {
+        (other.stars, self.moves, self.seconds) < (self.stars, other.moves, other.seconds)
+    }
+
+    pub fn stars_text(&self) -> String {
+        (0..3)
+            .map(|i| if i < self.stars { "*" } else { "-" })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// Events
+#[derive(Event, Debug)]
+#[allow(dead_code)]
+pub struct LevelScored {
+    pub level: LevelState,
+    pub result: LevelResult,
+    pub new_best: bool,
+}
+
+// Systems
+fn start_score(mut commands: Commands) {
+    debug!("starting {}", NAME);
+    commands.init_resource::<LevelStats>();
+}
+
+fn reset_stats(mut commands: Commands, mut started: EventReader<LevelStarted>) {
+    for _ in started.read() {
+        debug!("resetting level stats {}", NAME);
+        commands.insert_resource(LevelStats::default());
+    }
+}
+
+fn count_moves(
+    mut stats: ResMut<LevelStats>,
+    mut moved: EventReader<MoveTriggered>,
+    mut blocked: EventReader<MoveBlocked>,
+    mut stalled: EventReader<MoveStalled>,
+) {
+    // a turn stuck in the mud is a move too
+    let moves = (moved.read().filter(|m| !m.pushed).count() + stalled.read().count()) as u32;
+    let bumps = blocked.read().count() as u32;
+    if moves > 0 || bumps > 0 {
+        stats.moves += moves;
+        stats.blocked += bumps;
+    }
+}
+
+fn tick_level_timer(mut stats: ResMut<LevelStats>, time: Res<Time>) {
+    stats.timer.tick(time.delta());
+}
+
+fn score_level(
+    mut exits: EventReader<ExitReached>,
+    stats: Res<LevelStats>,
+    par: Option<Res<LevelPar>>,
+    mut best_results: ResMut<BestResults>,
+    mut scored: EventWriter<LevelScored>,
+) {
+    // several players could reach the exit in the same frame, score the level once
+    let Some(exit) = exits.read().last() else {
+        return;
+    };
+    let result = LevelResult::new(&stats, par.as_deref());
+    let new_best = best_results
+        .0
+        .get(&exit.0)
+        .is_none_or(|best| result.is_better_than(best));
+    debug!("{:?} scored {:?}, new best: {}", exit.0, result, new_best);
+    if new_best {
+        best_results.0.insert(exit.0, result);
+        storage::save(BEST_RESULTS_FILE, best_results.as_ref());
+    }
+    scored.write(LevelScored {
+        level: exit.0,
+        result,
+        new_best,
+    });
+}
+
+fn show_result_card(
+    mut commands: Commands,
+    mut scored: EventReader<LevelScored>,
+    cards: Query<Entity, With<ResultCard>>,
+    par: Option<Res<LevelPar>>,
+) {
+    for s in scored.read() {
+        for card in cards.iter() {
+            commands.entity(card).despawn();
+        }
+        let par_text = par
+            .as_ref()
+            .map(|par| format!("par: {} moves, {:.0}s", par.moves, par.seconds))
+            .unwrap_or_default();
+        let best_text = if s.new_best { "new best!" } else { "" };
+        let lines = [
+            format!("{:?} complete", s.level),
+            s.result.stars_text(),
+            format!("{} moves, {:.1}s", s.result.moves, s.result.seconds),
+            par_text,
+            best_text.to_string(),
+        ];
+        commands.spawn(result_card()).with_children(|parent| {
+            for line in lines.into_iter().filter(|line| !line.is_empty()) {
+                parent.spawn(Text::new(line));
+            }
+        });
+    }
+}
+
+fn hide_result_card(
+    mut commands: Commands,
+    mut cards: Query<(Entity, &mut ResultCard)>,
+    time: Res<Time>,
+) {
+    for (entity, mut card) in cards.iter_mut() {
+        if card.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn stop_score(mut commands: Commands) {
+    debug!("stopping {}", NAME);
+    commands.remove_resource::<LevelStats>();
+    commands.remove_resource::<LevelPar>();
+}
+
+// helper functions
+fn result_card() -> impl Bundle + use<> {
+    (
+        ResultCard(Timer::new(
+            Duration::from_secs_f32(RESULT_CARD_SECONDS),
+            TimerMode::Once,
+        )),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            top: Val::Percent(30.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(4.0),
+            ..default()
+        },
+    )
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    fn stats(moves: u32, blocked: u32, seconds: f32) -> LevelStats {
+        let mut stats = LevelStats {
+            moves,
+            blocked,
+            ..default()
+        };
+        stats.timer.tick(Duration::from_secs_f32(seconds));
+        stats
+    }
+
+    #[test]
+    fn should_rate_level_against_par() {
+        // given
+        let par = LevelPar {
+            moves: 6,
+            seconds: 10.0,
+        };
+
+        // when
+        let perfect = LevelResult::new(&stats(5, 1, 8.0), Some(&par));
+        let slow = LevelResult::new(&stats(5, 0, 12.0), Some(&par));
+        let clumsy = LevelResult::new(&stats(5, 3, 12.0), Some(&par));
+        let without_par = LevelResult::new(&stats(1, 0, 1.0), None);
+
+        // then
+        assert_eq!(perfect.stars, 3);
+        assert_eq!(slow.stars, 2);
+        assert_eq!(clumsy.stars, 1);
+        assert_eq!(without_par.stars, 1);
+        assert_eq!(perfect.stars_text(), "* * *");
+        assert_eq!(clumsy.stars_text(), "* - -");
+    }
+
+    #[test]
+    fn should_prefer_more_stars_then_fewer_moves() {
+        // given
+        let best = LevelResult {
+            stars: 2,
+            moves: 8,
+            seconds: 5.0,
+        };
+
+        // when
+        let more_stars = LevelResult {
+            stars: 3,
+            moves: 10,
+            seconds: 9.0,
+        };
+        let fewer_moves = LevelResult { moves: 7, ..best };
+        let slower = LevelResult {
+            seconds: 6.0,
+            ..best
+        };
+
+        // then
+        assert!(more_stars.is_better_than(&best));
+        assert!(fewer_moves.is_better_than(&best));
+        assert!(!slower.is_better_than(&best));
+        assert!(!best.is_better_than(&best));
+    }
+}
@@ -0,0 +1,544 @@
+use bevy::{
+    app::Plugin,
+    asset::{AssetLoader, LoadContext, io::Reader},
+    ecs::system::SystemParam,
+    platform::collections::HashSet,
+};
+
+use AppState::Running;
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    app_states::{AppState, LevelState},
+    gold::PlayerPickedUpGoldCoins,
+    health::{PickedUpEmptyHeart, PickedUpHearts},
+    movement::{InteractionTriggered, MoveStalled, MoveTriggered},
+    sprites::{SpawnSprite, SpriteSheetTile},
+    tiles::{DoorOpened, DoorTile, TileCoordinate, TileLayer},
+    transition::StartTransition,
+    tutorial::Tutorial,
+    tween::Tween,
+};
+
+// Constants
+const NAME: &str = "scripting";
+
+// Plugin
+pub struct ScriptingPlugin;
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // assets
+            .init_asset::<LevelScript>()
+            .init_asset_loader::<LevelScriptLoader>()
+            // systems
+            .add_systems(OnEnter(Running), start_scripting)
+            .add_systems(
+                Update,
+                (
+                    on_level_start,
+                    on_pickup,
+                    on_interact,
+                    on_move,
+                    update_script_texts,
+                )
+                    .run_if(in_state(Running)),
+            )
+            .add_systems(OnExit(Running), stop_scripting);
+    }
+}
+
+// Assets
+/// sandboxed level script, loaded from `*.script.ron` files next to the level.
+/// scripts can only use the [ScriptAction]s below, they never get direct world access.
+#[derive(Asset, TypePath, Deserialize, Debug, Default)]
+pub struct LevelScript {
+    #[serde(default)]
+    pub on_level_start: Vec<ScriptRule>,
+    #[serde(default)]
+    pub on_pickup: Vec<ScriptRule>,
+    #[serde(default)]
+    pub on_interact: Vec<ScriptRule>,
+    #[serde(default)]
+    pub on_move: Vec<ScriptRule>,
+}
+
+/// actions to run when a hook fires and the condition holds
+#[derive(Deserialize, Debug, Clone)]
+pub struct ScriptRule {
+    #[serde(default)]
+    pub when: ScriptCondition,
+    /// only fire the first time the condition holds
+    #[serde(default)]
+    pub once: bool,
+    pub actions: Vec<ScriptAction>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub enum ScriptCondition {
+    #[default]
+    Always,
+    /// pickup hook only: what has been picked up
+    PickedUp(PickupKind),
+    /// move and interact hooks only: target tile of the move or the interaction
+    At(i32, i32),
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickupKind {
+    Gold,
+    Hearts,
+    EmptyHeart,
+}
+
+/// safe api exposed to scripts
+#[derive(Deserialize, Debug, Clone)]
+pub enum ScriptAction {
+    Spawn {
+        tile: SpriteSheetTile,
+        at: TileCoordinate,
+        #[serde(default)]
+        color: Option<(f32, f32, f32)>,
+        #[serde(default)]
+        tutorial: bool,
+        /// draw layer, the atlas layer when missing
+        #[serde(default)]
+        layer: Option<TileLayer>,
+    },
+    /// despawn everything on the cell, or only the given tile type
+    Despawn {
+        at: (i32, i32),
+        #[serde(default)]
+        tile: Option<SpriteSheetTile>,
+    },
+    /// mark tiles on a cell as tutorial, levels will highlight them
+    Highlight {
+        at: (i32, i32),
+        #[serde(default)]
+        tile: Option<SpriteSheetTile>,
+    },
+    /// stop highlighting all tutorial tiles
+    ClearTutorials,
+    ShowText {
+        text: String,
+        seconds: f32,
+    },
+    SetLevel(LevelState),
+    MainMenu,
+}
+
+#[derive(Default)]
+pub struct LevelScriptLoader;
+
+#[derive(Debug, Error)]
+pub enum LevelScriptLoaderError {
+    #[error("could not read level script: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse level script: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for LevelScriptLoader {
+    type Asset = LevelScript;
+    type Settings = ();
+    type Error = LevelScriptLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes::<LevelScript>(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["script.ron"]
+    }
+}
+
+// Components
+#[derive(Component)]
+struct ScriptText(Timer);
+
+// Resources
+/// script of the currently running level. levels insert this on start and remove it on stop.
+#[derive(Resource)]
+pub struct ActiveLevelScript {
+    handle: Handle<LevelScript>,
+    started: bool,
+    fired: HashSet<(Hook, usize)>,
+}
+
+impl ActiveLevelScript {
+    pub fn new(handle: Handle<LevelScript>) -> Self {
+        ActiveLevelScript {
+            handle,
+            started: false,
+            fired: HashSet::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Hook {
+    LevelStart,
+    Pickup,
+    Interact,
+    Move,
+}
+
+/// everything a script is allowed to touch
+#[derive(SystemParam)]
+struct ScriptApi<'w, 's> {
+    commands: Commands<'w, 's>,
+    spawn_sprite: EventWriter<'w, SpawnSprite>,
+    transition: EventWriter<'w, StartTransition>,
+    door_opened: EventWriter<'w, DoorOpened>,
+    tiles: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static TileCoordinate,
+            Option<&'static SpriteSheetTile>,
+        ),
+    >,
+    doors: Query<'w, 's, (&'static TileCoordinate, &'static Sprite), With<DoorTile>>,
+    tutorials: Query<'w, 's, Entity, With<Tutorial>>,
+}
+
+impl ScriptApi<'_, '_> {
+    fn run(&mut self, action: &ScriptAction) {
+        debug!("running script action {:?}", action);
+        match action {
+            ScriptAction::Spawn {
+                tile,
+                at,
+                color,
+                tutorial,
+                layer,
+            } => {
+                self.spawn_sprite.write(SpawnSprite {
+                    coordinate: at.clone(),
+                    tile: tile.clone(),
+                    color: color.map(|(r, g, b)| Color::linear_rgb(r, g, b)),
+                    tutorial: *tutorial,
+                    layer: *layer,
+                    ..default()
+                });
+            }
+            ScriptAction::Despawn { at, tile } => {
+                for entity in self.matching(*at, tile) {
+                    // despawned doors open like any other door
+                    if let Ok((coordinate, sprite)) = self.doors.get(entity) {
+                        self.door_opened.write(DoorOpened {
+                            coordinate: coordinate.clone(),
+                            sprite: sprite.clone(),
+                        });
+                    }
+                    self.commands.entity(entity).despawn();
+                }
+            }
+            ScriptAction::Highlight { at, tile } => {
+                for entity in self.matching(*at, tile) {
+                    self.commands.entity(entity).insert(Tutorial);
+                }
+            }
+            ScriptAction::ClearTutorials => {
+                for entity in self.tutorials.iter() {
+                    self.commands.entity(entity).remove::<(Tutorial, Tween)>();
+                }
+            }
+            ScriptAction::ShowText { text, seconds } => {
+                self.commands.spawn(script_text(text, *seconds));
+            }
+            ScriptAction::SetLevel(level) => {
+                self.transition.write(StartTransition::level(*level));
+            }
+            ScriptAction::MainMenu => {
+                self.transition
+                    .write(StartTransition::state(AppState::MainMenu));
+            }
+        }
+    }
+
+    fn matching(&self, at: (i32, i32), tile: &Option<SpriteSheetTile>) -> Vec<Entity> {
+        self.tiles
+            .iter()
+            .filter(|(_, c, _)| c.x == at.0 && c.y == at.1)
+            .filter(|(_, _, t)| tile.is_none() || tile.as_ref() == *t)
+            .map(|(e, _, _)| e)
+            .collect()
+    }
+}
+
+// Systems
+fn start_scripting(mut _commands: Commands) {
+    debug!("starting {}", NAME);
+}
+
+fn on_level_start(
+    mut active: Option<ResMut<ActiveLevelScript>>,
+    scripts: Res<Assets<LevelScript>>,
+    mut api: ScriptApi,
+) {
+    let Some(active) = active.as_deref_mut() else {
+        return;
+    };
+    if active.started {
+        return;
+    }
+    // scripts load asynchronously, so the hook fires as soon as the asset is there
+    if let Some(script) = scripts.get(&active.handle) {
+        debug!("running on_level_start {}", NAME);
+        active.started = true;
+        run_hook(
+            active,
+            Hook::LevelStart,
+            &script.on_level_start,
+            |c| c == &ScriptCondition::Always,
+            &mut api,
+        );
+    }
+}
+
+fn on_pickup(
+    mut active: Option<ResMut<ActiveLevelScript>>,
+    scripts: Res<Assets<LevelScript>>,
+    mut gold: EventReader<PlayerPickedUpGoldCoins>,
+    mut hearts: EventReader<PickedUpHearts>,
+    mut empty_hearts: EventReader<PickedUpEmptyHeart>,
+    mut api: ScriptApi,
+) {
+    let mut picked_up = Vec::new();
+    picked_up.extend(gold.read().map(|_| PickupKind::Gold));
+    picked_up.extend(hearts.read().map(|_| PickupKind::Hearts));
+    picked_up.extend(empty_hearts.read().map(|_| PickupKind::EmptyHeart));
+
+    let Some(active) = active.as_deref_mut() else {
+        return;
+    };
+    let Some(script) = scripts.get(&active.handle) else {
+        return;
+    };
+    for kind in picked_up {
+        run_hook(
+            active,
+            Hook::Pickup,
+            &script.on_pickup,
+            |c| matches!(c, ScriptCondition::Always) || c == &ScriptCondition::PickedUp(kind),
+            &mut api,
+        );
+    }
+}
+
+fn on_interact(
+    mut active: Option<ResMut<ActiveLevelScript>>,
+    scripts: Res<Assets<LevelScript>>,
+    mut triggered: EventReader<InteractionTriggered>,
+    coordinates: Query<&TileCoordinate>,
+    mut api: ScriptApi,
+) {
+    let targets: Vec<TileCoordinate> = triggered
+        .read()
+        .filter_map(|t| coordinates.get(t.interacted_with).ok().cloned())
+        .collect();
+
+    let Some(active) = active.as_deref_mut() else {
+        return;
+    };
+    let Some(script) = scripts.get(&active.handle) else {
+        return;
+    };
+    for target in targets {
+        run_hook(
+            active,
+            Hook::Interact,
+            &script.on_interact,
+            |c| is_at(c, &target),
+            &mut api,
+        );
+    }
+}
+
+fn on_move(
+    mut active: Option<ResMut<ActiveLevelScript>>,
+    scripts: Res<Assets<LevelScript>>,
+    mut moves: EventReader<MoveTriggered>,
+    mut stalled: EventReader<MoveStalled>,
+    mut api: ScriptApi,
+) {
+    // a turn stuck in the mud moves onto the same cell again
+    let targets: Vec<TileCoordinate> = moves
+        .read()
+        .map(|m| m.end.clone())
+        .chain(stalled.read().map(|s| s.at.clone()))
+        .collect();
+
+    let Some(active) = active.as_deref_mut() else {
+        return;
+    };
+    let Some(script) = scripts.get(&active.handle) else {
+        return;
+    };
+    for target in targets {
+        run_hook(
+            active,
+            Hook::Move,
+            &script.on_move,
+            |c| is_at(c, &target),
+            &mut api,
+        );
+    }
+}
+
+fn update_script_texts(
+    mut commands: Commands,
+    mut texts: Query<(Entity, &mut ScriptText)>,
+    time: Res<Time>,
+) {
+    for (entity, mut text) in texts.iter_mut() {
+        if text.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn stop_scripting(mut commands: Commands, texts: Query<Entity, With<ScriptText>>) {
+    debug!("stopping {}", NAME);
+    for text in texts.iter() {
+        commands.entity(text).despawn();
+    }
+    commands.remove_resource::<ActiveLevelScript>();
+}
+
+// helper functions
+fn run_hook(
+    active: &mut ActiveLevelScript,
+    hook: Hook,
+    rules: &[ScriptRule],
+    condition: impl Fn(&ScriptCondition) -> bool,
+    api: &mut ScriptApi,
+) {
+    for (index, rule) in rules.iter().enumerate() {
+        if !condition(&rule.when) || (rule.once && active.fired.contains(&(hook, index))) {
+            continue;
+        }
+        active.fired.insert((hook, index));
+        for action in rule.actions.iter() {
+            api.run(action);
+        }
+    }
+}
+
+fn is_at(condition: &ScriptCondition, coordinate: &TileCoordinate) -> bool {
+    match condition {
+        ScriptCondition::Always => true,
+        ScriptCondition::At(x, y) => coordinate.x == *x && coordinate.y == *y,
+        ScriptCondition::PickedUp(_) => false,
+    }
+}
+
+fn script_text(text: &str, seconds: f32) -> impl Bundle + use<> {
+    (
+        ScriptText(Timer::from_seconds(seconds, TimerMode::Once)),
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        children![(Text::new(text),)],
+    )
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn should_parse_level_script() {
+        // given
+        let source = r#"(
+            on_pickup: [(
+                when: PickedUp(Hearts),
+                once: true,
+                actions: [
+                    Despawn(at: (2, 1), tile: Some(MagicDoor)),
+                    Spawn(tile: LevelExit01, at: (x: 2, y: 1, z: 0)),
+                ],
+            )],
+        )"#;
+
+        // when
+        let script = ron::de::from_str::<LevelScript>(source).unwrap();
+
+        // then
+        assert!(script.on_level_start.is_empty());
+        assert_eq!(script.on_pickup.len(), 1);
+        assert!(script.on_pickup[0].once);
+        assert_eq!(
+            script.on_pickup[0].when,
+            ScriptCondition::PickedUp(PickupKind::Hearts)
+        );
+        assert_eq!(script.on_pickup[0].actions.len(), 2);
+    }
+
+    #[test]
+    fn should_fire_once_rules_only_once() {
+        // given
+        let mut app = App::new();
+        app.init_resource::<Assets<LevelScript>>();
+        app.add_event::<SpawnSprite>();
+        app.add_event::<StartTransition>();
+        app.add_event::<DoorOpened>();
+        app.add_event::<MoveTriggered>();
+        app.add_event::<MoveStalled>();
+        app.add_systems(Update, on_move);
+        let script = app
+            .world_mut()
+            .resource_mut::<Assets<LevelScript>>()
+            .add(LevelScript {
+                on_move: vec![ScriptRule {
+                    when: ScriptCondition::At(1, 0),
+                    once: true,
+                    actions: vec![ScriptAction::Spawn {
+                        tile: SpriteSheetTile::GoldCoin,
+                        at: TileCoordinate::default(),
+                        color: None,
+                        tutorial: false,
+                        layer: None,
+                    }],
+                }],
+                ..default()
+            });
+        app.insert_resource(ActiveLevelScript::new(script));
+        let mover = app.world_mut().spawn_empty().id();
+
+        // when
+        for _ in 0..2 {
+            app.world_mut()
+                .resource_mut::<Events<MoveTriggered>>()
+                .send(MoveTriggered {
+                    mover,
+                    start: TileCoordinate::default(),
+                    end: TileCoordinate { x: 1, y: 0, z: 0 },
+                    pushed: false,
+                });
+            app.update();
+        }
+
+        // then
+        let spawned = app.world().resource::<Events<SpawnSprite>>();
+        assert_eq!(spawned.len(), 1);
+    }
+}
@@ -0,0 +1,484 @@
+use bevy::{
+    app::Plugin,
+    audio::Volume,
+    window::{MonitorSelection, PrimaryWindow, VideoModeSelection, WindowMode},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_states::{MenuState, PauseState},
+    game_camera::GameCamera,
+    sprites::SPRITE_SCALE,
+    storage,
+};
+
+// Constants
+const NAME: &str = "settings";
+const SETTINGS_FILE: &str = "settings.ron";
+
+const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
+const PRESSED_BUTTON: Color = Color::srgb(0.35, 0.75, 0.35);
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+
+const RESOLUTIONS: [(u32, u32); 4] = [(1280, 720), (1600, 900), (1920, 1080), (2560, 1440)];
+
+// Plugin
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // resources
+            .insert_resource(storage::load::<Settings>(SETTINGS_FILE))
+            // systems
+            .add_systems(OnEnter(MenuState::Settings), start_settings_screen)
+            .add_systems(OnEnter(PauseState::Settings), start_settings_screen)
+            .add_systems(
+                Update,
+                (
+                    update_settings_screen,
+                    update_setting_values,
+                    apply_window_settings,
+                    apply_zoom,
+                    apply_volume,
+                )
+                    .chain(),
+            )
+            .add_systems(OnExit(MenuState::Settings), stop_settings_screen)
+            .add_systems(OnExit(PauseState::Settings), stop_settings_screen);
+    }
+}
+
+// Components
+#[derive(Component)]
+struct SettingsScreen;
+
+/// changes a setting by the given number of steps
+#[derive(Component, Debug)]
+struct SettingButton(SettingKind, i32);
+
+#[derive(Component)]
+struct SettingsBackButton;
+
+#[derive(Component)]
+struct SettingValue(SettingKind);
+
+// Resources
+/// player settings, persisted between runs and applied live
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub window_mode: WindowModeSetting,
+    pub resolution: (u32, u32),
+    /// multiplies the sprite scale
+    pub zoom: f32,
+    pub master_volume: f32,
+    pub effects_volume: f32,
+    /// multiplies the time animations advance per frame, 2.0 plays them twice as fast
+    pub animation_speed: f32,
+    /// share of the distance to the player the camera moves each frame, 1.0 is no smoothing
+    pub camera_follow: f32,
+    /// no screen shake, recoil or particles
+    pub reduced_motion: bool,
+    /// local co-op with a second player on the arrow keys or a second gamepad
+    pub players: u8,
+    /// the minimap only shows tiles the players came close to
+    pub minimap_explored_only: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            window_mode: WindowModeSetting::Windowed,
+            resolution: RESOLUTIONS[0],
+            zoom: 1.0,
+            master_volume: 1.0,
+            effects_volume: 1.0,
+            animation_speed: 1.0,
+            camera_follow: 0.125,
+            reduced_motion: false,
+            players: 1,
+            minimap_explored_only: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn step(&mut self, kind: SettingKind, steps: i32) {
+        let steps = steps as f32;
+        match kind {
+            SettingKind::WindowMode => {
+                let modes = WindowModeSetting::ALL;
+                let i = modes
+                    .iter()
+                    .position(|m| *m == self.window_mode)
+                    .unwrap_or(0);
+                self.window_mode = modes[cycle(i, steps as i32, modes.len())];
+            }
+            SettingKind::Resolution => {
+                let i = RESOLUTIONS
+                    .iter()
+                    .position(|r| *r == self.resolution)
+                    .unwrap_or(0);
+                self.resolution = RESOLUTIONS[cycle(i, steps as i32, RESOLUTIONS.len())];
+            }
+            SettingKind::Zoom => self.zoom = stepped(self.zoom, 0.25 * steps, 0.5, 2.0),
+            SettingKind::MasterVolume => {
+                self.master_volume = stepped(self.master_volume, 0.1 * steps, 0.0, 1.0)
+            }
+            SettingKind::EffectsVolume => {
+                self.effects_volume = stepped(self.effects_volume, 0.1 * steps, 0.0, 1.0)
+            }
+            SettingKind::AnimationSpeed => {
+                self.animation_speed = stepped(self.animation_speed, 0.25 * steps, 0.25, 3.0)
+            }
+            SettingKind::CameraFollow => {
+                self.camera_follow = stepped(self.camera_follow, 0.025 * steps, 0.025, 1.0)
+            }
+            SettingKind::ReducedMotion => self.reduced_motion = !self.reduced_motion,
+            SettingKind::Players => {
+                self.players =
+                    cycle(self.players.saturating_sub(1) as usize, steps as i32, 2) as u8 + 1
+            }
+            SettingKind::Minimap => self.minimap_explored_only = !self.minimap_explored_only,
+        }
+    }
+
+    pub fn value_text(&self, kind: SettingKind) -> String {
+        match kind {
+            SettingKind::WindowMode => format!("{:?}", self.window_mode),
+            SettingKind::Resolution => format!("{}x{}", self.resolution.0, self.resolution.1),
+            SettingKind::Zoom => format!("{:.1}", SPRITE_SCALE * self.zoom),
+            SettingKind::MasterVolume => format!("{:.0}%", self.master_volume * 100.0),
+            SettingKind::EffectsVolume => format!("{:.0}%", self.effects_volume * 100.0),
+            SettingKind::AnimationSpeed => format!("x{:.2}", self.animation_speed),
+            SettingKind::CameraFollow => format!("{:.3}", self.camera_follow),
+            SettingKind::ReducedMotion => if self.reduced_motion { "On" } else { "Off" }.into(),
+            SettingKind::Players => format!("{}", self.players),
+            SettingKind::Minimap => if self.minimap_explored_only {
+                "Explored"
+            } else {
+                "Everything"
+            }
+            .into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowModeSetting {
+    #[default]
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl WindowModeSetting {
+    const ALL: [WindowModeSetting; 3] = [
+        WindowModeSetting::Windowed,
+        WindowModeSetting::Borderless,
+        WindowModeSetting::Fullscreen,
+    ];
+
+    fn window_mode(&self) -> WindowMode {
+        match self {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::Borderless => {
+                WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+            }
+            WindowModeSetting::Fullscreen => {
+                WindowMode::Fullscreen(MonitorSelection::Current, VideoModeSelection::Current)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingKind {
+    WindowMode,
+    Resolution,
+    Zoom,
+    MasterVolume,
+    EffectsVolume,
+    AnimationSpeed,
+    CameraFollow,
+    ReducedMotion,
+    Players,
+    Minimap,
+}
+
+impl SettingKind {
+    const ALL: [SettingKind; 10] = [
+        SettingKind::WindowMode,
+        SettingKind::Resolution,
+        SettingKind::Zoom,
+        SettingKind::MasterVolume,
+        SettingKind::EffectsVolume,
+        SettingKind::AnimationSpeed,
+        SettingKind::CameraFollow,
+        SettingKind::ReducedMotion,
+        SettingKind::Players,
+        SettingKind::Minimap,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SettingKind::WindowMode => "Window mode",
+            SettingKind::Resolution => "Resolution",
+            SettingKind::Zoom => "Sprite zoom",
+            SettingKind::MasterVolume => "Master volume",
+            SettingKind::EffectsVolume => "Effects volume",
+            SettingKind::AnimationSpeed => "Animation speed",
+            SettingKind::CameraFollow => "Camera follow",
+            SettingKind::ReducedMotion => "Reduced motion",
+            SettingKind::Players => "Players",
+            SettingKind::Minimap => "Minimap",
+        }
+    }
+}
+
+// Events
+
+// Systems
+fn start_settings_screen(mut commands: Commands, settings: Res<Settings>) {
+    debug!("starting {}", NAME);
+    commands
+        .spawn((
+            SettingsScreen,
+            Name::new("Settings Screen"),
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+            GlobalZIndex(10),
+        ))
+        .with_children(|builder| {
+            builder.spawn((
+                Text::new("Settings"),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+            ));
+            builder
+                .spawn(Node {
+                    display: Display::Grid,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(8.0),
+                    column_gap: Val::Px(16.0),
+                    grid_template_columns: vec![
+                        GridTrack::auto(),
+                        GridTrack::auto(),
+                        GridTrack::px(160.0),
+                        GridTrack::auto(),
+                    ],
+                    ..default()
+                })
+                .with_children(|builder| {
+                    for kind in SettingKind::ALL {
+                        builder.spawn((Text::new(kind.label()), TextColor(TEXT_COLOR)));
+                        spawn_settings_button(builder, "<", SettingButton(kind, -1));
+                        builder
+                            .spawn(Node {
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            })
+                            .with_children(|builder| {
+                                builder.spawn((
+                                    SettingValue(kind),
+                                    Text::new(settings.value_text(kind)),
+                                    TextColor(TEXT_COLOR),
+                                ));
+                            });
+                        spawn_settings_button(builder, ">", SettingButton(kind, 1));
+                    }
+                });
+            spawn_settings_button(builder, "Back", SettingsBackButton);
+        });
+}
+
+fn update_settings_screen(
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            Option<&SettingButton>,
+            Has<SettingsBackButton>,
+        ),
+        (
+            Changed<Interaction>,
+            With<Button>,
+            Or<(With<SettingButton>, With<SettingsBackButton>)>,
+        ),
+    >,
+    mut settings: ResMut<Settings>,
+    menu_state: Option<Res<State<MenuState>>>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    for (interaction, mut color, setting_button, back) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = PRESSED_BUTTON.into();
+                if let Some(SettingButton(kind, steps)) = setting_button {
+                    settings.step(*kind, *steps);
+                    debug!("changed setting: {:?}", settings);
+                } else if back {
+                    // the settings screen is shared by the main menu and the pause menu
+                    if menu_state.is_some() {
+                        next_menu_state.set(MenuState::Main);
+                    } else {
+                        next_pause_state.set(PauseState::Paused);
+                    }
+                }
+            }
+            Interaction::Hovered => *color = HOVERED_BUTTON.into(),
+            Interaction::None => *color = NORMAL_BUTTON.into(),
+        }
+    }
+}
+
+fn update_setting_values(settings: Res<Settings>, mut values: Query<(&mut Text, &SettingValue)>) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (mut text, value) in values.iter_mut() {
+        text.0 = settings.value_text(value.0);
+    }
+}
+
+fn apply_window_settings(
+    settings: Res<Settings>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for mut window in windows.iter_mut() {
+        let mode = settings.window_mode.window_mode();
+        if window.mode != mode {
+            debug!("window mode: {:?}", mode);
+            window.mode = mode;
+        }
+        let (width, height) = settings.resolution;
+        if window.resolution.width() != width as f32 || window.resolution.height() != height as f32
+        {
+            debug!("window resolution: {}x{}", width, height);
+            window.resolution.set(width as f32, height as f32);
+        }
+    }
+}
+
+fn apply_zoom(
+    settings: Res<Settings>,
+    mut projections: Query<&mut Projection, (With<Camera2d>, Without<GameCamera>)>,
+) {
+    // cameras could be spawned after the settings changed, the game camera zooms on its own
+    for mut projection in projections.iter_mut() {
+        if let Projection::Orthographic(ortho) = projection.as_mut() {
+            let scale = 1.0 / settings.zoom;
+            if ortho.scale != scale {
+                ortho.scale = scale;
+            }
+        }
+    }
+}
+
+fn apply_volume(settings: Res<Settings>, global_volume: Option<ResMut<GlobalVolume>>) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(mut global_volume) = global_volume {
+        global_volume.volume = Volume::Linear(settings.master_volume);
+    }
+}
+
+fn stop_settings_screen(
+    mut commands: Commands,
+    screens: Query<Entity, With<SettingsScreen>>,
+    settings: Res<Settings>,
+) {
+    debug!("stopping {}", NAME);
+    for screen in screens.iter() {
+        commands.entity(screen).despawn();
+    }
+    storage::save(SETTINGS_FILE, settings.as_ref());
+}
+
+// helper functions
+fn spawn_settings_button(builder: &mut ChildSpawnerCommands, text: &str, button: impl Component) {
+    builder
+        .spawn((
+            Button,
+            button,
+            Node {
+                padding: UiRect::axes(Val::Px(12.), Val::Px(4.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(NORMAL_BUTTON),
+        ))
+        .with_children(|parent| {
+            parent.spawn((Text::new(text), TextColor(TEXT_COLOR)));
+        });
+}
+
+/// wraps around at both ends
+fn cycle(index: usize, steps: i32, len: usize) -> usize {
+    (index as i32 + steps).rem_euclid(len as i32) as usize
+}
+
+/// rounds to the step to avoid drifting floats in the saved file
+fn stepped(value: f32, step: f32, min: f32, max: f32) -> f32 {
+    ((value + step).clamp(min, max) * 1000.0).round() / 1000.0
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn should_step_settings_within_bounds() {
+        // given
+        let mut settings = Settings::default();
+
+        // when
+        settings.step(SettingKind::MasterVolume, 3);
+        settings.step(SettingKind::EffectsVolume, -12);
+        settings.step(SettingKind::WindowMode, -1);
+        settings.step(SettingKind::Zoom, 1);
+
+        // then
+        assert_eq!(settings.master_volume, 1.0);
+        assert_eq!(settings.effects_volume, 0.0);
+        assert_eq!(settings.window_mode, WindowModeSetting::Fullscreen);
+        assert_eq!(settings.value_text(SettingKind::Zoom), "7.5");
+    }
+
+    #[test]
+    fn should_read_partial_settings_file() {
+        // given
+        let file = "(zoom: 1.5)";
+
+        // when
+        let settings: Settings = ron::de::from_str(file).unwrap();
+
+        // then
+        assert_eq!(settings.zoom, 1.5);
+        assert_eq!(settings.camera_follow, 0.125);
+    }
+}
@@ -1,19 +1,24 @@
-use std::{fmt::Display, time::Duration};
-
 use bevy::app::Plugin;
 use log::debug;
+use serde::Deserialize;
 
 use crate::{
     AppState::Running,
+    atlas::{TileCatalog, TileTrait},
     controls::PlayerControlled,
+    dialogue::NpcTile,
     gold::Gold,
     health::{EmptyHeart, Hearts},
-    tiles::{DoorTile, FloorTile, InteractableTile, SolidTile, Tile, TriggerTile},
+    tiles::{
+        DoorTile, FloorBehaviour, FloorTile, InteractableTile, Pit, SolidTile, Tile, TriggerTile,
+    },
     tutorial::Tutorial,
 };
 use bevy::prelude::*;
 
-use crate::tiles::TileCoordinate;
+use crate::paths::{Hazard, PathFollower, Platform, Pusher, TilePath};
+use crate::teleport::Teleporter;
+use crate::tiles::{TileCoordinate, TileLayer};
 
 // Constants
 const NAME: &str = "sprites";
@@ -21,19 +26,14 @@ const NAME: &str = "sprites";
 pub const SPRITE_DIM: u32 = 16;
 pub const SPRITE_SCALE: f32 = 6.0;
 
-const X_TILES: u32 = 49;
-const Y_TILES: u32 = 22;
-const GAP: u32 = 1;
-
-const TILE_SHEET_FILE: &str = "Tilesheet/monochrome-transparent.png";
-pub const ANIM_DURATION: u64 = 200;
-
 // Enums
-#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
+#[derive(Component, Debug, Default, Clone, Eq, PartialEq, Hash, Deserialize)]
 #[allow(dead_code)]
 pub enum SpriteSheetTile {
     // creature sprites
     Player01,
+    Player02,
+    Npc01,
     // exit tiles
     LevelExit01,
     // doors
@@ -50,8 +50,31 @@ pub enum SpriteSheetTile {
     Grass,
     GrassFlowers,
     LongGrass,
+    // special floor tiles
+    Ice,
+    Mud,
+    ConveyorUp,
+    ConveyorDown,
+    ConveyorLeft,
+    ConveyorRight,
+    OneWayUp,
+    OneWayDown,
+    OneWayLeft,
+    OneWayRight,
+    // teleporters, linked with the other tiles of the same kind
+    TeleporterA,
+    TeleporterB,
+    // light sources
+    Torch,
+    // moving things on paths and the pits they cross
+    Sawblade,
+    PushBlock,
+    Platform,
+    Pit,
     // walls
     BrickWall01,
+    /// picks its sprite from the neighbouring walls, see [`crate::autotile`]
+    Wall,
     // health / hearts
     Heart,
     EmptyHeart,
@@ -71,63 +94,8 @@ pub enum SpriteSheetTile {
     RightDigiPadRound,
     UpDigiPadRound,
     DownDigiPadRound,
-}
-
-impl SpriteSheetTile {
-    pub fn index(&self) -> usize {
-        match self {
-            SpriteSheetTile::Player01 => SpriteSheetTile::get_index(30, 9),
-            SpriteSheetTile::LevelExit01 => SpriteSheetTile::get_index(2, 9),
-            SpriteSheetTile::LockedDoor => SpriteSheetTile::get_index(0, 9),
-            SpriteSheetTile::MagicDoor => SpriteSheetTile::get_index(1, 9),
-            SpriteSheetTile::OpenDoor => SpriteSheetTile::get_index(2, 9),
-            SpriteSheetTile::RegularDoor => SpriteSheetTile::get_index(3, 9),
-            SpriteSheetTile::MechanicDoor => SpriteSheetTile::get_index(4, 9),
-            SpriteSheetTile::BottomLeverLeft => SpriteSheetTile::get_index(3, 10),
-            SpriteSheetTile::BottomLeverRight => SpriteSheetTile::get_index(4, 10),
-            SpriteSheetTile::Grass => SpriteSheetTile::get_index(5, 0),
-            SpriteSheetTile::GrassFlowers => SpriteSheetTile::get_index(6, 0),
-            SpriteSheetTile::LongGrass => SpriteSheetTile::get_index(7, 0),
-            SpriteSheetTile::BrickWall01 => SpriteSheetTile::get_index(10, 17),
-            SpriteSheetTile::Heart => SpriteSheetTile::get_index(39, 10),
-            SpriteSheetTile::EmptyHeart => SpriteSheetTile::get_index(40, 10),
-            SpriteSheetTile::HalfHeart => SpriteSheetTile::get_index(41, 10),
-            SpriteSheetTile::FullHeart => SpriteSheetTile::get_index(42, 10),
-            SpriteSheetTile::GoldCoin => SpriteSheetTile::get_index(41, 3),
-            SpriteSheetTile::GoldCoins => SpriteSheetTile::get_index(41, 4),
-            SpriteSheetTile::GoldCoinBag => SpriteSheetTile::get_index(42, 4),
-            SpriteSheetTile::A => SpriteSheetTile::get_index(35, 18),
-            SpriteSheetTile::D => SpriteSheetTile::get_index(38, 18),
-            SpriteSheetTile::S => SpriteSheetTile::get_index(40, 19),
-            SpriteSheetTile::W => SpriteSheetTile::get_index(44, 19),
-            SpriteSheetTile::LeftDigiPadRound => SpriteSheetTile::get_index(47, 11),
-            SpriteSheetTile::RightDigiPadRound => SpriteSheetTile::get_index(45, 11),
-            SpriteSheetTile::UpDigiPadRound => SpriteSheetTile::get_index(44, 11),
-            SpriteSheetTile::DownDigiPadRound => SpriteSheetTile::get_index(46, 11),
-        }
-    }
-
-    fn get_index(x: usize, y: usize) -> usize {
-        y * X_TILES as usize + x
-    }
-
-    fn color(&self) -> Color {
-        match self {
-            SpriteSheetTile::LevelExit01 => Color::linear_rgb(0.0, 1.0, 1.0),
-            SpriteSheetTile::Grass => Color::linear_rgb(0.0, 1.0, 0.0),
-            SpriteSheetTile::GrassFlowers => Color::linear_rgb(0.2, 1.0, 0.2),
-            SpriteSheetTile::LongGrass => Color::linear_rgb(0.0, 1.0, 0.0),
-            SpriteSheetTile::BrickWall01 => Color::linear_rgb(0.5, 0.1, 0.1),
-            SpriteSheetTile::Heart => Color::linear_rgb(1.0, 0.0, 0.0),
-            SpriteSheetTile::EmptyHeart => Color::linear_rgb(0.5, 0.0, 0.0),
-            SpriteSheetTile::HalfHeart => Color::linear_rgb(0.75, 0.0, 0.0),
-            SpriteSheetTile::FullHeart => Color::linear_rgb(1.0, 0.0, 0.0),
-            SpriteSheetTile::GoldCoin => Color::linear_rgb(0.6, 0.6, 0.0),
-            SpriteSheetTile::GoldCoins => Color::linear_rgb(0.6, 0.6, 0.0),
-            SpriteSheetTile::GoldCoinBag => Color::linear_rgb(0.6, 0.6, 0.0),
-            _ => Color::default(),
-        }
-    }
+    /// tiles only known to the atlas files, see [`crate::atlas`]
+    Custom(String),
 }
 
 // Plugin
@@ -137,16 +105,9 @@ impl Plugin for SpritesPlugin {
     fn build(&self, app: &mut App) {
         app
             // events
-            .add_event::<MoveAnimationFinished>()
             .add_event::<SpawnSprite>()
             // systems
-            .add_systems(OnEnter(Running), setup)
-            .add_systems(
-                Update,
-                (update_animation_timer, cleanup_animations, spawn_sprite)
-                    .run_if(in_state(Running)),
-            )
-            .add_systems(OnExit(Running), cleanup);
+            .add_systems(Update, (spawn_sprite).run_if(in_state(Running)));
     }
 }
 
@@ -157,225 +118,104 @@ pub struct MySprite;
 #[derive(Component)]
 pub struct ExfilSprite;
 
-#[derive(Component)]
-pub struct MoveAnimation {
-    pub timer: Timer,
-    pub function: EaseFunction,
-    pub start: TileCoordinate,
-    pub end: TileCoordinate,
-}
-
-impl Display for MoveAnimation {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "Animation: start: {}, end: {}, timer fraction: {}, timer remaining: {}",
-            self.start,
-            self.end,
-            self.timer.fraction(),
-            self.timer.remaining_secs()
-        )
-    }
-}
-
-impl Default for MoveAnimation {
-    fn default() -> Self {
-        Self {
-            timer: Timer::new(Duration::from_millis(ANIM_DURATION), TimerMode::Once),
-            function: EaseFunction::CircularInOut,
-            start: Default::default(),
-            end: Default::default(),
-        }
-    }
-}
-
 // Resources
-#[derive(Resource, Clone)]
-pub struct SpritesheetTexture(pub Handle<Image>);
-
-#[derive(Resource, Clone)]
-pub struct SpritesheetTextureAtlasLayout(pub Handle<TextureAtlasLayout>);
 
 // Events
-#[derive(Event)]
-pub struct MoveAnimationFinished(Entity);
-
-#[derive(Event, Default)]
+#[derive(Event, Default, Clone)]
 pub struct SpawnSprite {
     pub coordinate: TileCoordinate,
     pub tile: SpriteSheetTile,
-    /// custom color, will override defaults
+    /// custom color, will override the atlas tint
     pub color: Option<Color>,
+    /// custom draw layer, will override the atlas layer
+    pub layer: Option<TileLayer>,
+    /// despawned together with the spawn, so the cell is never empty and nothing points to a
+    /// despawned entity in between
+    pub replaces: Option<Entity>,
+    /// makes the sprite walk the path
+    pub path: Option<TilePath>,
     pub tutorial: bool,
 }
 
-impl SpawnSprite {
-    fn color(&self) -> Color {
-        self.color.unwrap_or(self.tile.color())
-    }
-}
-
 // Systems
-pub fn setup(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
-) {
-    debug!("starting {}", NAME);
-
-    let layout = TextureAtlasLayout::from_grid(
-        UVec2::splat(SPRITE_DIM),
-        X_TILES,
-        Y_TILES,
-        Some(UVec2::splat(GAP)),
-        None,
-    );
-    commands.insert_resource(SpritesheetTextureAtlasLayout(
-        texture_atlas_layouts.add(layout),
-    ));
-    commands.insert_resource(SpritesheetTexture(asset_server.load(TILE_SHEET_FILE)));
-}
-
-fn cleanup(mut commands: Commands) {
-    // TODO: do i need to cleanup the assets too?
-    commands.remove_resource::<SpritesheetTexture>();
-    commands.remove_resource::<SpritesheetTextureAtlasLayout>();
-}
-
 fn spawn_sprite(
     mut commands: Commands,
     mut spawn_coordinate: EventReader<SpawnSprite>,
-    sprite_sheet: Res<SpritesheetTexture>,
-    sprite_sheet_texture_atlas_layout: Res<SpritesheetTextureAtlasLayout>,
+    catalog: Option<Res<TileCatalog>>,
+    mut pending: Local<Vec<SpawnSprite>>,
 ) {
-    for spawn_sprite in spawn_coordinate.read() {
+    debug!("updating {}", NAME);
+    pending.extend(spawn_coordinate.read().cloned());
+    // levels can start before the atlases are loaded, their sprites wait for the catalog
+    let Some(catalog) = catalog else {
+        return;
+    };
+    for spawn_sprite in pending.drain(..) {
         debug!(
             "spawning sprite {:?} on coordinate: {}",
             spawn_sprite.tile, spawn_sprite.coordinate
         );
+        let Some(tile) = catalog.get(&spawn_sprite.tile) else {
+            warn!("no atlas defines the tile {:?}", spawn_sprite.tile);
+            continue;
+        };
+        if let Some(replaced) = spawn_sprite.replaces {
+            commands.entity(replaced).try_despawn();
+        }
 
         // sprite
-        let transform: Transform = spawn_sprite.coordinate.clone().into();
-        let new_sprite = commands
-            .spawn((
-                MySprite,
-                Sprite {
-                    image: sprite_sheet.0.clone(),
-                    color: spawn_sprite.color(),
-                    texture_atlas: Some(TextureAtlas {
-                        layout: sprite_sheet_texture_atlas_layout.0.clone(),
-                        index: spawn_sprite.tile.index(),
-                    }),
-                    ..default()
-                },
-                transform,
-                spawn_sprite.coordinate.clone(),
-            ))
-            .id();
-
-        // custom marker components
-        match spawn_sprite.tile {
-            SpriteSheetTile::LevelExit01 => {
-                commands.entity(new_sprite).insert(ExfilSprite);
-                commands.entity(new_sprite).insert(Tile);
-            }
-            SpriteSheetTile::Player01 => {
-                commands.entity(new_sprite).insert(PlayerControlled);
-            }
-            SpriteSheetTile::LockedDoor => {
-                commands.entity(new_sprite).insert(SolidTile);
-                commands.entity(new_sprite).insert(InteractableTile);
-                commands.entity(new_sprite).insert(DoorTile);
-            }
-            SpriteSheetTile::MagicDoor => {
-                commands.entity(new_sprite).insert(SolidTile);
-                commands.entity(new_sprite).insert(InteractableTile);
-                commands.entity(new_sprite).insert(DoorTile);
-            }
-            SpriteSheetTile::OpenDoor => {
-                commands.entity(new_sprite).insert(DoorTile);
-            }
-            SpriteSheetTile::MechanicDoor => {
-                commands.entity(new_sprite).insert(SolidTile);
-                commands.entity(new_sprite).insert(InteractableTile);
-                commands.entity(new_sprite).insert(DoorTile);
-            }
-            SpriteSheetTile::RegularDoor => {
-                commands.entity(new_sprite).insert(SolidTile);
-                commands.entity(new_sprite).insert(InteractableTile);
-                commands.entity(new_sprite).insert(DoorTile);
-            }
-            SpriteSheetTile::BottomLeverLeft => {
-                commands.entity(new_sprite).insert(SolidTile);
-                commands.entity(new_sprite).insert(InteractableTile);
-                commands.entity(new_sprite).insert(TriggerTile);
-            }
-            SpriteSheetTile::BottomLeverRight => {
-                commands.entity(new_sprite).insert(SolidTile);
-                commands.entity(new_sprite).insert(InteractableTile);
-                commands.entity(new_sprite).insert(TriggerTile);
-            }
-            SpriteSheetTile::Heart => {
-                commands.entity(new_sprite).insert(Hearts(1));
-            }
-            SpriteSheetTile::EmptyHeart => {
-                commands.entity(new_sprite).insert(EmptyHeart);
-            }
-            SpriteSheetTile::GoldCoin => {
-                commands.entity(new_sprite).insert(Gold { coins: 1 });
-            }
-            SpriteSheetTile::GoldCoins => {
-                commands.entity(new_sprite).insert(Gold { coins: 5 });
-            }
-            SpriteSheetTile::GoldCoinBag => {
-                commands.entity(new_sprite).insert(Gold { coins: 25 });
-            }
-            SpriteSheetTile::BrickWall01 => {
-                commands.entity(new_sprite).insert(SolidTile);
-                commands.entity(new_sprite).insert(Tile);
-            }
-            SpriteSheetTile::Grass => {
-                commands.entity(new_sprite).insert(Tile);
-                commands.entity(new_sprite).insert(FloorTile);
-            }
-            SpriteSheetTile::GrassFlowers => {
-                commands.entity(new_sprite).insert(Tile);
-                commands.entity(new_sprite).insert(FloorTile);
-            }
-            SpriteSheetTile::LongGrass => {
-                commands.entity(new_sprite).insert(Tile);
-                commands.entity(new_sprite).insert(FloorTile);
-            }
-            _ => (),
+        let mut sprite = tile.sprite();
+        if let Some(color) = spawn_sprite.color {
+            sprite.color = color;
         }
-
-        if spawn_sprite.tutorial {
-            commands.entity(new_sprite).insert(Tutorial);
+        let coordinate = spawn_sprite
+            .coordinate
+            .clone()
+            .on(spawn_sprite.layer.unwrap_or(tile.layer));
+        let transform: Transform = coordinate.clone().into();
+        let mut new_sprite = commands.spawn((
+            MySprite,
+            sprite,
+            transform,
+            coordinate,
+            spawn_sprite.tile.clone(),
+        ));
+
+        // gameplay components from the atlas
+        for t in tile.traits.iter() {
+            match *t {
+                TileTrait::Tile => new_sprite.insert(Tile),
+                TileTrait::Floor => new_sprite.insert(FloorTile),
+                TileTrait::Solid => new_sprite.insert(SolidTile),
+                TileTrait::Interactable => new_sprite.insert(InteractableTile),
+                TileTrait::Door => new_sprite.insert(DoorTile),
+                TileTrait::Trigger => new_sprite.insert(TriggerTile),
+                TileTrait::Npc => new_sprite.insert(NpcTile),
+                TileTrait::Exit => new_sprite.insert(ExfilSprite),
+                TileTrait::Player(id) => new_sprite.insert(PlayerControlled(id)),
+                TileTrait::Gold(coins) => new_sprite.insert(Gold { coins }),
+                TileTrait::Hearts(hearts) => new_sprite.insert(Hearts(hearts)),
+                TileTrait::EmptyHeart => new_sprite.insert(EmptyHeart),
+                TileTrait::Ice => new_sprite.insert(FloorBehaviour::Ice),
+                TileTrait::Conveyor(d) => new_sprite.insert(FloorBehaviour::Conveyor(d)),
+                TileTrait::OneWay(d) => new_sprite.insert(FloorBehaviour::OneWay(d)),
+                TileTrait::Mud => new_sprite.insert(FloorBehaviour::Mud),
+                TileTrait::Teleporter(group) => new_sprite.insert(Teleporter { group }),
+                TileTrait::Hazard(damage) => new_sprite.insert(Hazard { damage }),
+                TileTrait::Pusher => new_sprite.insert(Pusher),
+                TileTrait::Platform => new_sprite.insert(Platform),
+                TileTrait::Pit => new_sprite.insert(Pit),
+                TileTrait::Light(light) => new_sprite.insert(light),
+            };
         }
-    }
-}
-
-fn update_animation_timer(
-    mut animations: Query<(Entity, &mut MoveAnimation)>,
-    time: Res<Time>,
-    mut animation_finished: EventWriter<MoveAnimationFinished>,
-) {
-    debug!("updating move animation {}", NAME);
-    for (entity, mut animation) in animations.iter_mut() {
-        animation.timer.tick(time.delta());
 
-        if animation.timer.finished() {
-            animation_finished.write(MoveAnimationFinished(entity));
+        if let Some(path) = spawn_sprite.path.as_ref() {
+            new_sprite.insert(PathFollower::new(path));
         }
-    }
-}
 
-fn cleanup_animations(
-    mut commands: Commands,
-    mut move_animations: EventReader<MoveAnimationFinished>,
-) {
-    for move_animation in move_animations.read() {
-        commands.entity(move_animation.0).remove::<MoveAnimation>();
+        if spawn_sprite.tutorial {
+            new_sprite.insert(Tutorial);
+        }
     }
 }
 
@@ -0,0 +1,52 @@
+//! small helpers to persist game data like progress and settings as ron files.
+//! web builds have no file system, they always start from the defaults.
+
+use bevy::prelude::*;
+use serde::{Serialize, de::DeserializeOwned};
+
+// Constants
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_DIR: &str = "save";
+
+// helper functions
+/// loads a saved file, falls back to the default if there is none or it can't be read
+pub fn load<T: DeserializeOwned + Default>(file: &str) -> T {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path = std::path::Path::new(SAVE_DIR).join(file);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => match ron::de::from_str(&content) {
+                Ok(value) => return value,
+                Err(e) => warn!("could not parse {}: {}", path.display(), e),
+            },
+            Err(e) => debug!("could not read {}: {}", path.display(), e),
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    debug!("no storage on the web, using defaults for {}", file);
+    T::default()
+}
+
+/// saves a file, errors are logged and otherwise ignored
+pub fn save<T: Serialize>(file: &str, value: &T) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path = std::path::Path::new(SAVE_DIR).join(file);
+        let result = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                std::fs::create_dir_all(SAVE_DIR)
+                    .and_then(|_| std::fs::write(&path, content))
+                    .map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(_) => debug!("saved {}", path.display()),
+            Err(e) => warn!("could not save {}: {}", path.display(), e),
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = value;
+        debug!("no storage on the web, not saving {}", file);
+    }
+}
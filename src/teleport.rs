@@ -0,0 +1,229 @@
+use std::time::Duration;
+
+use bevy::{app::Plugin, ecs::event::EventCursor};
+
+use AppState::Running;
+use bevy::prelude::*;
+
+use crate::{
+    app_states::AppState,
+    grid::{GridIndex, GridRole},
+    movement::{MoveTriggered, handle_input},
+    tiles::TileCoordinate,
+    tween::{ANIM_DURATION, Tween, TweenTarget, TweenTrack},
+};
+
+// Constants
+const NAME: &str = "teleport";
+
+// Plugin
+pub struct TeleportPlugin;
+
+impl Plugin for TeleportPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // events
+            .add_event::<Teleported>()
+            // systems
+            .add_systems(
+                Update,
+                (teleport_movers)
+                    .after(handle_input)
+                    .run_if(in_state(Running)),
+            );
+    }
+}
+
+// Components
+/// moves whoever ends a move on it to the next free teleporter of the same group
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Teleporter {
+    pub group: u8,
+}
+
+// Resources
+
+// Events
+#[derive(Event, Debug)]
+#[allow(dead_code)]
+pub struct Teleported {
+    pub mover: Entity,
+    pub from: TileCoordinate,
+    pub to: TileCoordinate,
+}
+
+// Systems
+/// the teleport is a move onto the exit too, so exits and scripts see it.
+/// that move ends on a teleporter as well and must not teleport again.
+#[allow(clippy::too_many_arguments)]
+fn teleport_movers(
+    mut commands: Commands,
+    mut moves: ResMut<Events<MoveTriggered>>,
+    mut read: Local<EventCursor<MoveTriggered>>,
+    mut arrivals: Local<Vec<(Entity, IVec2)>>,
+    teleporters: Query<(Entity, &Teleporter, &TileCoordinate)>,
+    mut movers: Query<&mut TileCoordinate, Without<Teleporter>>,
+    mut grid: ResMut<GridIndex>,
+    mut teleported: EventWriter<Teleported>,
+) {
+    debug!("updating {}", NAME);
+    let ended: Vec<(Entity, TileCoordinate, TileCoordinate)> = read
+        .read(&moves)
+        .map(|m| (m.mover, m.start.clone(), m.end.clone()))
+        .collect();
+    let last_arrivals = std::mem::take(&mut *arrivals);
+    for (mover, start, end) in ended {
+        if last_arrivals.contains(&(mover, end.cell())) {
+            continue;
+        }
+        let Some((entry, teleporter)) = grid
+            .with_role(end.cell(), GridRole::Teleporter)
+            .find_map(|e| teleporters.get(e).ok().map(|(e, t, _)| (e, *t)))
+        else {
+            continue;
+        };
+        let Ok(mut coordinate) = movers.get_mut(mover) else {
+            continue;
+        };
+        // the mover already left, e.g. pushed on by a conveyor in the same frame
+        if coordinate.cell() != end.cell() {
+            continue;
+        }
+
+        let mut group: Vec<(Entity, IVec2)> = teleporters
+            .iter()
+            .filter(|(_, t, _)| **t == teleporter)
+            .map(|(e, _, c)| (e, c.cell()))
+            .collect();
+        group.sort_by_key(|(e, _)| *e);
+        let Some(destination) = next_free(&group, entry, |cell| {
+            grid.first(cell, GridRole::Solid).is_none()
+                && grid.with_role(cell, GridRole::Player).all(|e| e == mover)
+        }) else {
+            debug!(
+                "every exit of teleporter group {} is blocked",
+                teleporter.group
+            );
+            continue;
+        };
+
+        let from = coordinate.clone();
+        let to = TileCoordinate {
+            x: destination.x,
+            y: destination.y,
+            z: from.z,
+        };
+        debug!("teleporting {} from {} to {}", mover, from, to);
+        commands
+            .entity(mover)
+            .insert(teleport_tween(&start, &from, &to));
+        grid.place(mover, &to);
+        *coordinate = to.clone();
+        moves.send(MoveTriggered {
+            mover,
+            start: from.clone(),
+            end: to.clone(),
+            pushed: true,
+        });
+        arrivals.push((mover, to.cell()));
+        teleported.write(Teleported { mover, from, to });
+    }
+}
+
+// helper functions
+/// the teleporters after the entry in order, starting over at the first one
+fn next_free(
+    group: &[(Entity, IVec2)],
+    entry: Entity,
+    free: impl Fn(IVec2) -> bool,
+) -> Option<IVec2> {
+    let at = group.iter().position(|(e, _)| *e == entry)?;
+    group
+        .iter()
+        .cycle()
+        .skip(at + 1)
+        .take(group.len() - 1)
+        .map(|(_, cell)| *cell)
+        .find(|cell| free(*cell))
+}
+
+/// walks onto the entry, vanishes and appears on the exit.
+/// replaces the move tween, which was inserted in the same frame.
+fn teleport_tween(start: &TileCoordinate, entry: &TileCoordinate, exit: &TileCoordinate) -> Tween {
+    let duration = Duration::from_millis(ANIM_DURATION);
+    let entry: Vec3 = entry.clone().into();
+    let exit: Vec3 = exit.clone().into();
+    Tween::new(TweenTrack::sequence(vec![
+        TweenTrack::step(
+            TweenTarget::Translation(start.clone().into(), entry),
+            duration,
+            EaseFunction::CircularInOut,
+        ),
+        TweenTrack::parallel(vec![
+            TweenTrack::step(TweenTarget::Scale(1.0, 0.0), duration, EaseFunction::BackIn),
+            TweenTrack::step(
+                TweenTarget::SpriteAlpha(1.0, 0.0),
+                duration,
+                EaseFunction::Linear,
+            ),
+        ]),
+        TweenTrack::parallel(vec![
+            TweenTrack::step(
+                TweenTarget::Translation(exit, exit),
+                Duration::ZERO,
+                EaseFunction::Linear,
+            ),
+            TweenTrack::popup(duration),
+        ]),
+    ]))
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use crate::{grid::GridPlugin, tiles::SolidTile};
+
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn should_teleport_to_next_free_teleporter_of_the_group() {
+        // given
+        let mut app = App::new();
+        app.add_plugins(GridPlugin);
+        app.add_event::<MoveTriggered>().add_event::<Teleported>();
+        app.add_systems(Update, teleport_movers);
+        let group = Teleporter { group: 1 };
+        for x in [0, 4, 8] {
+            app.world_mut().spawn((group, TileCoordinate::new(x, 0)));
+        }
+        app.world_mut()
+            .spawn((Teleporter { group: 2 }, TileCoordinate::new(2, 0)));
+        app.world_mut()
+            .spawn((SolidTile, TileCoordinate::new(4, 0)));
+        let mover = app.world_mut().spawn(TileCoordinate::new(0, 0)).id();
+
+        // when
+        app.world_mut().send_event(MoveTriggered {
+            mover,
+            start: TileCoordinate::new(-1, 0),
+            end: TileCoordinate::new(0, 0),
+            pushed: false,
+        });
+        app.update();
+        app.update();
+
+        // then
+        let coordinate = app.world().get::<TileCoordinate>(mover).unwrap();
+        assert_eq!(coordinate.cell(), IVec2::new(8, 0));
+        assert!(app.world().get::<Tween>(mover).is_some());
+        let moves = app.world().resource::<Events<MoveTriggered>>();
+        // the move onto the exit, it didn't teleport again on the next update
+        let ends: Vec<IVec2> = moves
+            .get_cursor()
+            .read(moves)
+            .map(|m| m.end.cell())
+            .collect();
+        assert_eq!(ends, vec![IVec2::new(8, 0)]);
+    }
+}
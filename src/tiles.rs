@@ -4,6 +4,7 @@ use bevy::app::Plugin;
 
 use AppState::Running;
 use bevy::prelude::*;
+use serde::Deserialize;
 
 use crate::{
     app_states::AppState,
@@ -18,7 +19,8 @@ pub struct TilesPlugin;
 
 impl Plugin for TilesPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(Running), start_tiles)
+        app.add_event::<DoorOpened>()
+            .add_systems(OnEnter(Running), start_tiles)
             .add_systems(Update, (logging_tiles).run_if(in_state(Running)))
             .add_systems(OnExit(Running), stop_tiles);
     }
@@ -31,17 +33,68 @@ impl Plugin for TilesPlugin {
 #[derive(Component)]
 pub struct Tile;
 
-#[derive(Component, PartialEq, Eq, Hash, Clone, Default, Debug)]
+#[derive(Component, PartialEq, Eq, Hash, Clone, Default, Debug, Deserialize)]
 pub struct TileCoordinate {
     pub x: i32,
     pub y: i32,
-    /// will be used for z depth
+    /// draw depth, set from the [TileLayer] of the tile when it is spawned
+    #[serde(default)]
     pub z: i32,
 }
 
 impl TileCoordinate {
+    pub fn new(x: i32, y: i32) -> Self {
+        TileCoordinate { x, y, z: 0 }
+    }
+
+    pub fn on(self, layer: TileLayer) -> Self {
+        TileCoordinate {
+            z: layer.z(),
+            ..self
+        }
+    }
+
+    /// the cell on the level grid, collision and pickups only look at this
+    pub fn cell(&self) -> IVec2 {
+        IVec2::new(self.x, self.y)
+    }
+
+    /// the neighbouring coordinate on the same layer
+    pub fn offset(&self, offset: IVec2) -> Self {
+        TileCoordinate {
+            x: self.x + offset.x,
+            y: self.y + offset.y,
+            z: self.z,
+        }
+    }
+
     pub fn eq2d(&self, other: &TileCoordinate) -> bool {
-        self.x.eq(&other.x) && self.y.eq(&other.y)
+        self.cell() == other.cell()
+    }
+}
+
+/// named draw layers, later layers are drawn on top of earlier ones
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TileLayer {
+    /// floors and exits
+    Ground,
+    /// walls, doors, levers and pickups
+    #[default]
+    Objects,
+    /// players and npcs
+    Actors,
+    /// tutorial hints and markers
+    Overlay,
+}
+
+impl TileLayer {
+    pub fn z(&self) -> i32 {
+        match self {
+            TileLayer::Ground => 0,
+            TileLayer::Objects => 1,
+            TileLayer::Actors => 2,
+            TileLayer::Overlay => 3,
+        }
     }
 }
 
@@ -90,9 +143,95 @@ pub struct InteractableTile;
 #[derive(Component)]
 pub struct TriggerTile;
 
+/// nobody can stand on it without a platform over it, see [`crate::paths`]
+#[derive(Component)]
+pub struct Pit;
+
+/// floors that change how movers move over them, to be used in combination with [FloorTile]
+#[derive(Component, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloorBehaviour {
+    /// movers keep sliding until something blocks them
+    Ice,
+    /// pushes movers standing on it one tile per tick
+    Conveyor(Direction),
+    /// can only be entered moving in the direction
+    OneWay(Direction),
+    /// leaving costs an extra turn
+    Mud,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    pub fn from_offset(offset: IVec2) -> Option<Direction> {
+        match (offset.x.signum(), offset.y.signum()) {
+            (0, 1) => Some(Direction::Up),
+            (0, -1) => Some(Direction::Down),
+            (-1, 0) => Some(Direction::Left),
+            (1, 0) => Some(Direction::Right),
+            (-1, 1) => Some(Direction::UpLeft),
+            (1, 1) => Some(Direction::UpRight),
+            (-1, -1) => Some(Direction::DownLeft),
+            (1, -1) => Some(Direction::DownRight),
+            _ => None,
+        }
+    }
+
+    pub fn offset(&self) -> IVec2 {
+        match self {
+            Direction::Up => IVec2::Y,
+            Direction::Down => IVec2::NEG_Y,
+            Direction::Left => IVec2::NEG_X,
+            Direction::Right => IVec2::X,
+            Direction::UpLeft => IVec2::new(-1, 1),
+            Direction::UpRight => IVec2::ONE,
+            Direction::DownLeft => IVec2::NEG_ONE,
+            Direction::DownRight => IVec2::new(1, -1),
+        }
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        let offset = self.offset();
+        offset.x != 0 && offset.y != 0
+    }
+
+    /// the diagonal between two perpendicular directions
+    pub fn combine(&self, other: Direction) -> Option<Direction> {
+        if self.is_diagonal() || other.is_diagonal() || self.offset().dot(other.offset()) != 0 {
+            return None;
+        }
+        Direction::from_offset(self.offset() + other.offset())
+    }
+
+    /// the horizontal and the vertical part of a diagonal, whose corners it passes
+    pub fn corners(&self) -> Option<(Direction, Direction)> {
+        let offset = self.offset();
+        Some((
+            Direction::from_offset(IVec2::new(offset.x, 0))?,
+            Direction::from_offset(IVec2::new(0, offset.y))?,
+        ))
+    }
+}
+
 // Resources
 
 // Events
+/// a door was removed from the level, carries its last look for effects
+#[derive(Event, Debug)]
+pub struct DoorOpened {
+    pub coordinate: TileCoordinate,
+    pub sprite: Sprite,
+}
 
 // Systems
 fn start_tiles(mut _commands: Commands) {
@@ -141,4 +280,19 @@ mod tests {
             vec3
         );
     }
+
+    #[test]
+    fn should_draw_layers_in_order_on_the_same_cell() {
+        // given
+        let coordinate = TileCoordinate::new(2, 3);
+
+        // when
+        let floor = coordinate.clone().on(TileLayer::Ground);
+        let player = coordinate.clone().on(TileLayer::Actors);
+
+        // then
+        assert!(floor.z < player.z);
+        assert!(floor.eq2d(&player));
+        assert_ne!(floor, player);
+    }
 }
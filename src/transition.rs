@@ -0,0 +1,397 @@
+use bevy::app::Plugin;
+
+use AppState::Running;
+use bevy::prelude::*;
+
+use crate::{
+    app_states::{AppState, LevelState},
+    controls::{ControlLock, ControlLocks},
+};
+
+// Constants
+const NAME: &str = "transition";
+
+const COVER_SECONDS: f32 = 0.4;
+const HOLD_SECONDS: f32 = 0.15;
+const TITLE_CARD_SECONDS: f32 = 1.2;
+
+const CURTAIN_COLOR: Color = Color::BLACK;
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+
+/// outer diameter of the iris ring in vmax, its hole covers the screen when open
+const IRIS_SIZE: f32 = 300.0;
+/// half the screen diagonal in vmax is a bit less than this
+const IRIS_OPEN_RADIUS: f32 = 75.0;
+
+// Plugin
+pub struct TransitionPlugin;
+
+impl Plugin for TransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // events
+            .add_event::<StartTransition>()
+            // systems
+            .add_systems(PreUpdate, start_transition)
+            .add_systems(
+                Update,
+                (update_transition).run_if(resource_exists::<Transition>),
+            );
+    }
+}
+
+// Components
+#[derive(Component)]
+struct TransitionScreen;
+
+#[derive(Component)]
+struct Curtain;
+
+#[derive(Component)]
+struct TitleCardText;
+
+// Resources
+/// the running transition, new requests are ignored until it is done
+#[derive(Resource, Debug)]
+pub struct Transition {
+    request: StartTransition,
+    phase: TransitionPhase,
+    timer: Timer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TransitionPhase {
+    /// the curtain closes over the old level
+    Covering,
+    /// the state changed, the new level spawns behind the curtain
+    Holding,
+    /// the curtain opens on the new level
+    Revealing,
+}
+
+// Events
+/// covers the screen, changes the state at the midpoint and reveals the screen again
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct StartTransition {
+    pub to: TransitionTarget,
+    pub effect: TransitionEffect,
+    pub title_card: Option<TitleCard>,
+}
+
+impl StartTransition {
+    /// to a level, with its name on a title card
+    pub fn level(level: LevelState) -> Self {
+        let number = LevelState::ALL
+            .iter()
+            .position(|l| *l == level)
+            .unwrap_or(0)
+            + 1;
+        StartTransition {
+            to: TransitionTarget::Level(level),
+            effect: TransitionEffect::default(),
+            title_card: Some(TitleCard {
+                title: level.name().to_string(),
+                subtitle: format!("Level {}", number),
+            }),
+        }
+    }
+
+    pub fn state(state: AppState) -> Self {
+        StartTransition {
+            to: TransitionTarget::State(state),
+            effect: TransitionEffect::default(),
+            title_card: None,
+        }
+    }
+
+    pub fn with_effect(mut self, effect: TransitionEffect) -> Self {
+        self.effect = effect;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransitionTarget {
+    /// starts running if needed
+    Level(LevelState),
+    State(AppState),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TransitionEffect {
+    #[default]
+    Fade,
+    /// a circle closing to the center
+    Iris,
+    /// a curtain sliding in from the right and out to the left
+    Slide,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TitleCard {
+    pub title: String,
+    pub subtitle: String,
+}
+
+// Systems
+fn start_transition(
+    mut commands: Commands,
+    mut requests: EventReader<StartTransition>,
+    transition: Option<Res<Transition>>,
+    mut locks: ResMut<ControlLocks>,
+) {
+    // exits are checked every frame, the first request wins
+    let Some(request) = requests.read().next().cloned() else {
+        return;
+    };
+    requests.clear();
+    if transition.is_some() {
+        return;
+    }
+    debug!("starting {} to {:?}", NAME, request.to);
+    locks.0.insert(ControlLock::Transition);
+    spawn_transition_screen(&mut commands, &request);
+    commands.insert_resource(Transition {
+        request,
+        phase: TransitionPhase::Covering,
+        timer: Timer::from_seconds(COVER_SECONDS, TimerMode::Once),
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_transition(
+    mut commands: Commands,
+    mut transition: ResMut<Transition>,
+    time: Res<Time<Real>>,
+    app_state: Res<State<AppState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut next_level: ResMut<NextState<LevelState>>,
+    mut locks: ResMut<ControlLocks>,
+    mut curtains: Query<(&mut Node, &mut BackgroundColor), With<Curtain>>,
+    mut title_cards: Query<&mut Visibility, With<TitleCardText>>,
+    screens: Query<Entity, With<TransitionScreen>>,
+) {
+    debug!("updating {}", NAME);
+    // real time, the game time could be paused
+    transition.timer.tick(time.delta());
+    let fraction = EaseFunction::SineInOut.sample_clamped(transition.timer.fraction());
+    let offset = match transition.phase {
+        TransitionPhase::Covering => 1.0 - fraction,
+        TransitionPhase::Holding => 0.0,
+        TransitionPhase::Revealing => -fraction,
+    };
+    for (mut node, mut background) in curtains.iter_mut() {
+        draw_curtain(
+            transition.request.effect,
+            offset,
+            &mut node,
+            &mut background,
+        );
+    }
+    for mut visibility in title_cards.iter_mut() {
+        *visibility = if transition.phase == TransitionPhase::Holding {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+
+    if !transition.timer.finished() {
+        return;
+    }
+    match transition.phase {
+        TransitionPhase::Covering => {
+            // the old level despawns on exit, the new one spawns on enter
+            match &transition.request.to {
+                TransitionTarget::Level(level) => {
+                    // setting the same state would restart it
+                    if *app_state.get() != Running {
+                        next_app_state.set(Running);
+                    }
+                    next_level.set(*level);
+                }
+                TransitionTarget::State(state) => next_app_state.set(state.clone()),
+            }
+            let hold = if transition.request.title_card.is_some() {
+                TITLE_CARD_SECONDS
+            } else {
+                HOLD_SECONDS
+            };
+            transition.phase = TransitionPhase::Holding;
+            transition.timer = Timer::from_seconds(hold, TimerMode::Once);
+        }
+        TransitionPhase::Holding => {
+            transition.phase = TransitionPhase::Revealing;
+            transition.timer = Timer::from_seconds(COVER_SECONDS, TimerMode::Once);
+        }
+        TransitionPhase::Revealing => {
+            debug!("stopping {}", NAME);
+            for screen in screens.iter() {
+                commands.entity(screen).despawn();
+            }
+            commands.remove_resource::<Transition>();
+            locks.0.remove(&ControlLock::Transition);
+        }
+    }
+}
+
+// helper functions
+/// run condition for systems that must not request another transition, like exit checks
+pub fn no_transition(transition: Option<Res<Transition>>) -> bool {
+    transition.is_none()
+}
+
+fn spawn_transition_screen(commands: &mut Commands, request: &StartTransition) {
+    let mut curtain = match request.effect {
+        TransitionEffect::Fade | TransitionEffect::Slide => Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+        TransitionEffect::Iris => Node {
+            width: Val::VMax(IRIS_SIZE),
+            height: Val::VMax(IRIS_SIZE),
+            flex_shrink: 0.0,
+            ..default()
+        },
+    };
+    let mut background = BackgroundColor(Color::NONE);
+    draw_curtain(request.effect, 1.0, &mut curtain, &mut background);
+    commands
+        .spawn((
+            TransitionScreen,
+            Name::new("Transition Screen"),
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                overflow: Overflow::clip(),
+                ..default()
+            },
+            // above menus and dialogues
+            GlobalZIndex(20),
+        ))
+        .with_children(|builder| {
+            let mut curtain_entity = builder.spawn((Curtain, curtain, background));
+            if request.effect == TransitionEffect::Iris {
+                curtain_entity.insert((BorderColor(CURTAIN_COLOR), BorderRadius::MAX));
+            }
+            if let Some(title_card) = &request.title_card {
+                builder
+                    .spawn((
+                        TitleCardText,
+                        Node {
+                            position_type: PositionType::Absolute,
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            row_gap: Val::Px(8.0),
+                            ..default()
+                        },
+                        Visibility::Hidden,
+                    ))
+                    .with_children(|builder| {
+                        builder.spawn((
+                            Text::new(title_card.title.clone()),
+                            TextFont {
+                                font_size: 48.0,
+                                ..default()
+                            },
+                            TextColor(TEXT_COLOR),
+                        ));
+                        builder.spawn((
+                            Text::new(title_card.subtitle.clone()),
+                            TextFont {
+                                font_size: 24.0,
+                                ..default()
+                            },
+                            TextColor(TEXT_COLOR),
+                        ));
+                    });
+            }
+        });
+}
+
+/// offset is 1.0 when the screen is open before covering, 0.0 when covered and -1.0 when open
+/// after revealing
+fn draw_curtain(
+    effect: TransitionEffect,
+    offset: f32,
+    node: &mut Node,
+    background: &mut BackgroundColor,
+) {
+    let open = offset.abs();
+    match effect {
+        TransitionEffect::Fade => {
+            background.0 = CURTAIN_COLOR.with_alpha(1.0 - open);
+        }
+        TransitionEffect::Slide => {
+            background.0 = CURTAIN_COLOR;
+            node.left = Val::Percent(offset * 100.0);
+        }
+        TransitionEffect::Iris => {
+            // the rounded ring keeps a round hole, that shrinks as the border grows
+            let border = IRIS_SIZE / 2.0 - IRIS_OPEN_RADIUS * open;
+            node.border = UiRect::all(Val::VMax(border));
+        }
+    }
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::state::app::StatesPlugin;
+
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    fn advance(app: &mut App, seconds: f32) {
+        app.world_mut()
+            .resource_mut::<Time<Real>>()
+            .update_with_duration(Duration::from_secs_f32(seconds));
+        app.update();
+    }
+
+    #[test]
+    fn should_change_level_behind_the_curtain_and_unlock_controls_after() {
+        // given
+        let mut app = App::new();
+        app.add_plugins(StatesPlugin);
+        app.insert_state(AppState::Running);
+        app.add_sub_state::<LevelState>();
+        app.init_resource::<ControlLocks>();
+        app.init_resource::<Time<Real>>();
+        app.add_event::<StartTransition>();
+        app.add_systems(PreUpdate, start_transition);
+        app.add_systems(
+            Update,
+            (update_transition).run_if(resource_exists::<Transition>),
+        );
+        app.update();
+
+        // when
+        app.world_mut().send_event(StartTransition {
+            title_card: None,
+            ..StartTransition::level(LevelState::Level02)
+        });
+        advance(&mut app, 0.0);
+        let locked = app.world().resource::<ControlLocks>().0.clone();
+        let level_while_covering = *app.world().resource::<State<LevelState>>().get();
+        advance(&mut app, COVER_SECONDS);
+        advance(&mut app, 0.0);
+        let level_behind_curtain = *app.world().resource::<State<LevelState>>().get();
+        advance(&mut app, HOLD_SECONDS);
+        advance(&mut app, COVER_SECONDS);
+
+        // then
+        assert!(locked.contains(&ControlLock::Transition));
+        assert_eq!(level_while_covering, LevelState::Level01);
+        assert_eq!(level_behind_curtain, LevelState::Level02);
+        assert!(app.world().get_resource::<Transition>().is_none());
+        assert!(app.world().resource::<ControlLocks>().0.is_empty());
+    }
+}
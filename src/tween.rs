@@ -0,0 +1,568 @@
+use std::{fmt::Display, time::Duration};
+
+use bevy::app::Plugin;
+
+use bevy::prelude::*;
+
+use crate::settings::Settings;
+
+// Constants
+const NAME: &str = "tween";
+
+/// at normal animation speed, see [`Settings`]
+pub const ANIM_DURATION: u64 = 200;
+
+/// max rotation of a wiggle in radians
+const WIGGLE_ANGLE: f32 = 0.3;
+/// how much a pulse grows, relative to the scale
+const PULSE_SCALE: f32 = 0.04;
+
+// Plugin
+pub struct TweenPlugin;
+
+impl Plugin for TweenPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // events
+            .add_event::<TweenFinished>()
+            // observers
+            .add_observer(remember_origin)
+            .add_observer(restore_origin)
+            // systems
+            .add_systems(Update, (update_tweens, cleanup_tweens).chain());
+    }
+}
+
+// Components
+/// animates properties of its entity. an entity has one tween at a time, inserting a new one
+/// replaces the old one, combine them with [TweenTrack::parallel] or [Tween::join] instead.
+#[derive(Component, Debug, Clone)]
+pub struct Tween {
+    track: TweenTrack,
+    elapsed: Duration,
+    repeat: bool,
+    real_time: bool,
+    /// [TweenFinished] was sent
+    announced: bool,
+}
+
+impl Tween {
+    pub fn new(track: TweenTrack) -> Self {
+        Tween {
+            track,
+            elapsed: Duration::ZERO,
+            repeat: false,
+            real_time: false,
+            announced: false,
+        }
+    }
+
+    /// starts over when done, repeating tweens never finish
+    pub fn repeating(mut self) -> Self {
+        self.repeat = true;
+        self
+    }
+
+    /// keeps running while the game is paused, for ui
+    pub fn in_real_time(mut self) -> Self {
+        self.real_time = true;
+        self
+    }
+
+    /// runs another track from now on alongside the remaining one
+    pub fn join(&mut self, track: TweenTrack) {
+        let delay = TweenTrack::Delay(self.elapsed);
+        let old = std::mem::replace(&mut self.track, TweenTrack::Parallel(Vec::new()));
+        self.track = TweenTrack::parallel(vec![old, TweenTrack::sequence(vec![delay, track])]);
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.track.duration()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.repeat && self.elapsed >= self.duration()
+    }
+
+    fn tick(&mut self, delta: Duration) {
+        self.elapsed += delta;
+        let duration = self.duration();
+        if self.repeat && !duration.is_zero() && self.elapsed >= duration {
+            self.elapsed =
+                Duration::from_nanos((self.elapsed.as_nanos() % duration.as_nanos()) as u64);
+        }
+    }
+
+    /// eased fraction of every step that started, in order
+    fn sample(&self) -> Vec<(&TweenTarget, f32)> {
+        let mut samples = Vec::new();
+        self.track.sample(self.elapsed, &mut samples);
+        samples
+    }
+}
+
+impl Display for Tween {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Tween: elapsed: {:?}, duration: {:?}, repeat: {}",
+            self.elapsed,
+            self.duration(),
+            self.repeat
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TweenTrack {
+    Step {
+        target: TweenTarget,
+        duration: Duration,
+        ease: EaseFunction,
+    },
+    /// waits without animating anything
+    Delay(Duration),
+    /// one track after another
+    Sequence(Vec<TweenTrack>),
+    /// all tracks at once, done with the longest one
+    Parallel(Vec<TweenTrack>),
+}
+
+impl TweenTrack {
+    pub fn step(target: TweenTarget, duration: Duration, ease: EaseFunction) -> Self {
+        TweenTrack::Step {
+            target,
+            duration,
+            ease,
+        }
+    }
+
+    pub fn sequence(tracks: Vec<TweenTrack>) -> Self {
+        TweenTrack::Sequence(tracks)
+    }
+
+    pub fn parallel(tracks: Vec<TweenTrack>) -> Self {
+        TweenTrack::Parallel(tracks)
+    }
+
+    /// grows and shrinks back
+    pub fn pulse(duration: Duration) -> Self {
+        let half = duration / 2;
+        TweenTrack::sequence(vec![
+            TweenTrack::step(
+                TweenTarget::Scale(1.0, 1.0 + PULSE_SCALE),
+                half,
+                EaseFunction::SineInOut,
+            ),
+            TweenTrack::step(
+                TweenTarget::Scale(1.0 + PULSE_SCALE, 1.0),
+                half,
+                EaseFunction::SineInOut,
+            ),
+        ])
+    }
+
+    /// rotates left/right, settling down
+    pub fn wiggle(duration: Duration) -> Self {
+        let angles = [
+            0.0,
+            WIGGLE_ANGLE,
+            -WIGGLE_ANGLE,
+            WIGGLE_ANGLE * 0.5,
+            -WIGGLE_ANGLE * 0.5,
+            0.0,
+        ];
+        let part = duration / (angles.len() - 1) as u32;
+        TweenTrack::sequence(
+            angles
+                .windows(2)
+                .map(|w| {
+                    TweenTrack::step(
+                        TweenTarget::Rotation(w[0], w[1]),
+                        part,
+                        EaseFunction::SineInOut,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// scales up from nothing and fades in
+    pub fn popup(duration: Duration) -> Self {
+        TweenTrack::parallel(vec![
+            TweenTrack::step(
+                TweenTarget::Scale(0.0, 1.0),
+                duration,
+                EaseFunction::BackOut,
+            ),
+            TweenTrack::step(
+                TweenTarget::SpriteAlpha(0.0, 1.0),
+                duration,
+                EaseFunction::Linear,
+            ),
+        ])
+    }
+
+    /// blows up and fades out
+    pub fn burst(duration: Duration) -> Self {
+        TweenTrack::parallel(vec![
+            TweenTrack::step(
+                TweenTarget::Scale(1.0, 2.0),
+                duration,
+                EaseFunction::QuadraticOut,
+            ),
+            TweenTrack::step(
+                TweenTarget::SpriteAlpha(1.0, 0.0),
+                duration,
+                EaseFunction::Linear,
+            ),
+        ])
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            TweenTrack::Step { duration, .. } | TweenTrack::Delay(duration) => *duration,
+            TweenTrack::Sequence(tracks) => tracks.iter().map(|t| t.duration()).sum(),
+            TweenTrack::Parallel(tracks) => tracks
+                .iter()
+                .map(|t| t.duration())
+                .max()
+                .unwrap_or_default(),
+        }
+    }
+
+    fn sample<'a>(&'a self, elapsed: Duration, samples: &mut Vec<(&'a TweenTarget, f32)>) {
+        match self {
+            TweenTrack::Step {
+                target,
+                duration,
+                ease,
+            } => {
+                let fraction = if duration.is_zero() {
+                    1.0
+                } else {
+                    (elapsed.as_secs_f32() / duration.as_secs_f32()).min(1.0)
+                };
+                samples.push((target, ease.sample(fraction).unwrap_or(1.0)));
+            }
+            TweenTrack::Delay(_) => (),
+            TweenTrack::Sequence(tracks) => {
+                let mut start = Duration::ZERO;
+                for track in tracks {
+                    // later tracks did not start yet and must not overwrite earlier ones
+                    if elapsed < start {
+                        break;
+                    }
+                    track.sample(elapsed - start, samples);
+                    start += track.duration();
+                }
+            }
+            TweenTrack::Parallel(tracks) => {
+                for track in tracks {
+                    track.sample(elapsed, samples);
+                }
+            }
+        }
+    }
+}
+
+/// what a tween step animates, from start to end value
+#[derive(Debug, Clone, PartialEq)]
+pub enum TweenTarget {
+    Translation(Vec3, Vec3),
+    /// factor of the scale before the tween
+    Scale(f32, f32),
+    /// z rotation in radians, added to the rotation before the tween
+    Rotation(f32, f32),
+    SpriteColor(Color, Color),
+    /// factor of the sprite alpha before the tween
+    SpriteAlpha(f32, f32),
+    /// for ui nodes
+    BackgroundColor(Color, Color),
+}
+
+/// despawns the entity instead of removing the tween when it finished
+#[derive(Component)]
+pub struct DespawnAfterTween;
+
+/// scale, rotation and sprite color before the tween started, restored when the tween is removed
+#[derive(Component)]
+struct TweenOrigin {
+    scale: Vec3,
+    rotation: Quat,
+    alpha: f32,
+    color: Option<Color>,
+}
+
+// Resources
+
+// Events
+/// a tween ran to its end, repeating tweens never do
+#[derive(Event, Debug, PartialEq, Eq)]
+pub struct TweenFinished(pub Entity);
+
+// Systems
+fn remember_origin(
+    trigger: Trigger<OnInsert, Tween>,
+    mut commands: Commands,
+    tweened: Query<(Option<&Transform>, Option<&Sprite>, Has<TweenOrigin>)>,
+) {
+    let entity = trigger.target();
+    // a replaced tween keeps the origin of the first one
+    if let Ok((transform, sprite, false)) = tweened.get(entity) {
+        let transform = transform.copied().unwrap_or_default();
+        commands.entity(entity).insert(TweenOrigin {
+            scale: transform.scale,
+            rotation: transform.rotation,
+            alpha: sprite.map(|s| s.color.alpha()).unwrap_or(1.0),
+            color: sprite.map(|s| s.color),
+        });
+    }
+}
+
+fn restore_origin(
+    trigger: Trigger<OnRemove, Tween>,
+    mut commands: Commands,
+    mut tweened: Query<(&TweenOrigin, Option<&mut Transform>, Option<&mut Sprite>)>,
+) {
+    let entity = trigger.target();
+    if let Ok((origin, transform, sprite)) = tweened.get_mut(entity) {
+        // translations are absolute, moving tweens end where they should
+        if let Some(mut transform) = transform {
+            transform.rotation = origin.rotation;
+            transform.scale = origin.scale;
+        }
+        // a flash cut short by another tween must not stick
+        if let (Some(mut sprite), Some(color)) = (sprite, origin.color) {
+            sprite.color = color;
+        }
+        commands.entity(entity).try_remove::<TweenOrigin>();
+    }
+}
+
+fn update_tweens(
+    mut tweens: Query<(
+        Entity,
+        &mut Tween,
+        &TweenOrigin,
+        Option<&mut Transform>,
+        Option<&mut Sprite>,
+        Option<&mut BackgroundColor>,
+    )>,
+    time: Res<Time>,
+    real_time: Res<Time<Real>>,
+    settings: Res<Settings>,
+    mut finished: EventWriter<TweenFinished>,
+) {
+    debug!("updating {}", NAME);
+    for (entity, mut tween, origin, mut transform, mut sprite, mut background) in tweens.iter_mut()
+    {
+        let delta = if tween.real_time {
+            real_time.delta()
+        } else {
+            time.delta()
+        };
+        tween.tick(delta.mul_f32(settings.animation_speed));
+
+        for (target, f) in tween.sample() {
+            match target {
+                TweenTarget::Translation(start, end) => {
+                    if let Some(transform) = transform.as_mut() {
+                        transform.translation = start.lerp(*end, f);
+                    }
+                }
+                TweenTarget::Scale(start, end) => {
+                    if let Some(transform) = transform.as_mut() {
+                        transform.scale = origin.scale * start.lerp(*end, f);
+                    }
+                }
+                TweenTarget::Rotation(start, end) => {
+                    if let Some(transform) = transform.as_mut() {
+                        transform.rotation =
+                            origin.rotation * Quat::from_rotation_z(start.lerp(*end, f));
+                    }
+                }
+                TweenTarget::SpriteColor(start, end) => {
+                    if let Some(sprite) = sprite.as_mut() {
+                        sprite.color = start.mix(end, f);
+                    }
+                }
+                TweenTarget::SpriteAlpha(start, end) => {
+                    if let Some(sprite) = sprite.as_mut() {
+                        sprite.color.set_alpha(origin.alpha * start.lerp(*end, f));
+                    }
+                }
+                TweenTarget::BackgroundColor(start, end) => {
+                    if let Some(background) = background.as_mut() {
+                        background.0 = start.mix(end, f);
+                    }
+                }
+            }
+        }
+
+        if tween.is_finished() && !tween.announced {
+            tween.announced = true;
+            finished.write(TweenFinished(entity));
+        }
+    }
+}
+
+fn cleanup_tweens(
+    mut commands: Commands,
+    mut finished: EventReader<TweenFinished>,
+    tweens: Query<(&Tween, Has<DespawnAfterTween>)>,
+) {
+    for f in finished.read() {
+        match tweens.get(f.0) {
+            Ok((tween, true)) if tween.is_finished() => {
+                commands.entity(f.0).try_despawn();
+            }
+            // systems running alongside could insert a new tween before this command applies,
+            // so the check is repeated then
+            Ok((tween, false)) if tween.is_finished() => {
+                commands.entity(f.0).queue(|mut entity: EntityWorldMut| {
+                    if entity.get::<Tween>().is_some_and(|t| t.is_finished()) {
+                        entity.remove::<Tween>();
+                    }
+                });
+            }
+            _ => (),
+        }
+    }
+}
+
+// helper functions
+
+// tests
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn advance(app: &mut App, ms: u64) {
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(millis(ms));
+        app.update();
+    }
+
+    #[test]
+    fn should_sample_sequences_and_parallel_groups() {
+        // given
+        let tween = Tween::new(TweenTrack::sequence(vec![
+            TweenTrack::step(
+                TweenTarget::Scale(1.0, 2.0),
+                millis(100),
+                EaseFunction::Linear,
+            ),
+            TweenTrack::parallel(vec![
+                TweenTrack::step(
+                    TweenTarget::Rotation(0.0, 1.0),
+                    millis(100),
+                    EaseFunction::Linear,
+                ),
+                TweenTrack::step(
+                    TweenTarget::SpriteAlpha(1.0, 0.0),
+                    millis(50),
+                    EaseFunction::Linear,
+                ),
+            ]),
+        ]));
+
+        // when
+        let mut halfway = tween.clone();
+        halfway.tick(millis(50));
+        let mut late = tween.clone();
+        late.tick(millis(175));
+
+        // then
+        assert_eq!(tween.duration(), millis(200));
+        assert_eq!(halfway.sample(), vec![(&TweenTarget::Scale(1.0, 2.0), 0.5)]);
+        let late = late.sample();
+        assert_eq!(late.len(), 3);
+        assert_eq!(late[0].1, 1.0);
+        assert_eq!(late[1].1, 0.75);
+        assert_eq!(late[2].1, 1.0);
+    }
+
+    #[test]
+    fn should_finish_once_and_restore_transform() {
+        // given
+        let mut app = App::new();
+        app.add_event::<TweenFinished>();
+        app.add_observer(remember_origin);
+        app.add_observer(restore_origin);
+        app.add_systems(Update, (update_tweens, cleanup_tweens).chain());
+        app.init_resource::<Time>();
+        app.init_resource::<Time<Real>>();
+        app.init_resource::<Settings>();
+        let origin = Transform::from_scale(Vec3::splat(6.0));
+        let entity = app
+            .world_mut()
+            .spawn((
+                origin,
+                Sprite::default(),
+                Tween::new(TweenTrack::burst(millis(100))),
+            ))
+            .id();
+
+        // when
+        advance(&mut app, 50);
+        let faded = app.world().get::<Sprite>(entity).unwrap().color.alpha();
+        advance(&mut app, 50);
+        let finished = app
+            .world()
+            .resource::<Events<TweenFinished>>()
+            .iter_current_update_events()
+            .count();
+        advance(&mut app, 0);
+
+        // then
+        assert_eq!(faded, 0.5);
+        assert_eq!(finished, 1);
+        assert!(app.world().get::<Tween>(entity).is_none());
+        assert_eq!(*app.world().get::<Transform>(entity).unwrap(), origin);
+    }
+
+    #[test]
+    fn should_keep_a_tween_inserted_when_the_old_one_finished() {
+        // given
+        let mut app = App::new();
+        app.add_event::<TweenFinished>();
+        app.add_observer(remember_origin);
+        app.add_observer(restore_origin);
+        // another system inserts a tween before the cleanup commands apply
+        let retween = |mut commands: Commands, mut finished: EventReader<TweenFinished>| {
+            for f in finished.read() {
+                commands
+                    .entity(f.0)
+                    .insert(Tween::new(TweenTrack::burst(millis(100))));
+            }
+        };
+        app.add_systems(
+            Update,
+            (update_tweens, retween, cleanup_tweens).chain_ignore_deferred(),
+        );
+        app.init_resource::<Time>();
+        app.init_resource::<Time<Real>>();
+        app.init_resource::<Settings>();
+        let entity = app
+            .world_mut()
+            .spawn((
+                Transform::default(),
+                Tween::new(TweenTrack::burst(millis(100))),
+            ))
+            .id();
+
+        // when
+        advance(&mut app, 100);
+
+        // then
+        let tween = app.world().get::<Tween>(entity);
+        assert!(tween.is_some_and(|t| !t.is_finished()));
+    }
+}